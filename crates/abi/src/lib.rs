@@ -6,8 +6,8 @@ mod models;
 
 use models::*;
 use sprite_dicing::{
-    Artifacts, DicedSprite, Error, Pivot, Pixel, Prefs, Progress, Rect, SourceSprite, Texture, Uv,
-    Vertex,
+    Alias, Anchor, Artifacts, Border, DicedSprite, Error, Grid, Indices, Instance, Lod, Origin,
+    Pivot, Pixel, Prefs, Progress, Quantized, Rect, SourceSprite, Texture, Uv, Vertex,
 };
 use std::ffi::{CStr, CString, c_char};
 use std::mem;
@@ -36,14 +36,37 @@ fn to_prefs(c: CPrefs) -> Prefs {
         padding: c.padding,
         uv_inset: c.uv_inset,
         trim_transparent: c.trim_transparent,
+        reuse_threshold: c.reuse_threshold,
         atlas_size_limit: c.atlas_size_limit,
         atlas_square: c.atlas_square,
         atlas_pot: c.atlas_pot,
+        atlas_uniform: c.atlas_uniform,
+        atlas_balance: c.atlas_balance,
         ppu: c.ppu,
         pivot: Pivot {
             x: c.pivot.x,
             y: c.pivot.y,
         },
+        origin: if c.has_origin {
+            Some(to_origin(&c.origin))
+        } else {
+            None
+        },
+        y_up: c.y_up,
+        uv_bottom_left: c.uv_bottom_left,
+        clockwise: c.clockwise,
+        build_quads: c.build_quads,
+        weld_vertices: c.weld_vertices,
+        quantize: c.quantize,
+        build_instances: c.build_instances,
+        merge_instance_uvs: c.merge_instance_uvs,
+        build_indirection: c.build_indirection,
+        build_outline: c.build_outline,
+        outline_threshold: c.outline_threshold,
+        outline_tolerance: c.outline_tolerance,
+        lod_count: c.lod_count,
+        vertex_budget: c.vertex_budget,
+        alias_duplicates: c.alias_duplicates,
         on_progress: if c.has_progress_callback {
             Some(Box::new(move |p| unsafe {
                 (c.progress_callback)(to_c_progress(p))
@@ -58,17 +81,33 @@ fn to_c_err(e: Error) -> CResult {
     let error = to_c_str(&e.to_string());
     let ok = CArtifacts {
         atlases: CSlice::empty(),
+        occupancy: CSlice::empty(),
         sprites: CSlice::empty(),
+        aliases: CSlice::empty(),
     };
     CResult { error, ok }
 }
 
 fn to_c_ok(arts: Artifacts) -> CResult {
     let atlases = to_c_slice(arts.atlases.iter().map(to_c_texture).collect());
+    let occupancy = to_c_slice(arts.layouts.iter().map(|l| l.occupancy()).collect());
     let sprites = to_c_slice(arts.sprites.iter().map(to_c_sprite).collect());
+    let aliases = to_c_slice(arts.aliases.iter().map(to_c_alias).collect());
     CResult {
         error: to_c_str(""),
-        ok: CArtifacts { atlases, sprites },
+        ok: CArtifacts {
+            atlases,
+            occupancy,
+            sprites,
+            aliases,
+        },
+    }
+}
+
+fn to_c_alias(alias: &Alias) -> CAlias {
+    CAlias {
+        id: to_c_str(&alias.id),
+        target: to_c_str(&alias.target),
     }
 }
 
@@ -84,18 +123,152 @@ unsafe fn to_sprite(c: &CSourceSprite) -> SourceSprite {
         } else {
             None
         },
+        origin: if c.has_origin {
+            Some(to_origin(&c.origin))
+        } else {
+            None
+        },
+        group: if c.has_group {
+            Some(to_str(c.group).to_owned())
+        } else {
+            None
+        },
+        ppu: if c.has_ppu { Some(c.ppu) } else { None },
+        border: if c.has_border {
+            Some(Border {
+                left: c.border.left,
+                top: c.border.top,
+                right: c.border.right,
+                bottom: c.border.bottom,
+            })
+        } else {
+            None
+        },
     }
 }
 
+fn to_origin(c: &COrigin) -> Origin {
+    let anchor = match c.anchor {
+        0 => Anchor::TopLeft,
+        1 => Anchor::TopCenter,
+        2 => Anchor::TopRight,
+        3 => Anchor::MiddleLeft,
+        4 => Anchor::Center,
+        5 => Anchor::MiddleRight,
+        6 => Anchor::BottomLeft,
+        7 => Anchor::BottomCenter,
+        8 => Anchor::BottomRight,
+        _ => return Origin::Pixel(c.x, c.y),
+    };
+    Origin::Anchor(anchor)
+}
+
 fn to_c_sprite(sprite: &DicedSprite) -> CDicedSprite {
     CDicedSprite {
         id: to_c_str(&sprite.id),
         atlas_index: sprite.atlas_index as u64,
+        atlas_indices: to_c_slice(sprite.atlas_indices.iter().map(|i| *i as u64).collect()),
+        layer: sprite.layer as u64,
         vertices: to_c_slice(sprite.vertices.iter().map(to_c_vertex).collect()),
         uvs: to_c_slice(sprite.uvs.iter().map(to_c_uv).collect()),
-        indices: to_c_slice(sprite.indices.iter().map(|i| *i as u64).collect()),
+        indices_u16: match &sprite.indices {
+            Indices::U16(indices) => to_c_slice(indices.to_owned()),
+            Indices::U32(_) => CSlice::empty(),
+        },
+        indices_u32: match &sprite.indices {
+            Indices::U32(indices) => to_c_slice(indices.to_owned()),
+            Indices::U16(_) => CSlice::empty(),
+        },
         rect: to_c_rect(&sprite.rect),
         pivot: to_c_pivot(&sprite.pivot),
+        has_group: sprite.group.is_some(),
+        group: to_c_str(sprite.group.as_deref().unwrap_or("")),
+        whole: sprite.whole,
+        has_indirection: sprite.indirection.is_some(),
+        indirection: to_c_indirection(sprite.indirection.as_ref()),
+        has_grid: sprite.grid.is_some(),
+        grid: to_c_grid(sprite.grid.as_ref()),
+        instances: to_c_slice(sprite.instances.iter().map(to_c_instance).collect()),
+        instance_uvs: to_c_slice(sprite.instance_uvs.iter().map(to_c_rect).collect()),
+        has_quantized: sprite.quantized.is_some(),
+        quantized: to_c_quantized(sprite.quantized.as_ref()),
+        outline: to_c_slice(sprite.outline.iter().map(|p| to_c_path(p)).collect()),
+        has_border: sprite.border.is_some(),
+        border: to_c_border(sprite.border.as_ref()),
+        slices: to_c_slice(sprite.slices.to_owned()),
+        lods: to_c_slice(sprite.lods.iter().map(to_c_lod).collect()),
+    }
+}
+
+fn to_c_lod(lod: &Lod) -> CLod {
+    CLod {
+        atlas_indices: to_c_slice(lod.atlas_indices.iter().map(|i| *i as u64).collect()),
+        vertices: to_c_slice(lod.vertices.iter().map(to_c_vertex).collect()),
+        uvs: to_c_slice(lod.uvs.iter().map(to_c_uv).collect()),
+        indices_u16: match &lod.indices {
+            Indices::U16(indices) => to_c_slice(indices.to_owned()),
+            Indices::U32(_) => CSlice::empty(),
+        },
+        indices_u32: match &lod.indices {
+            Indices::U32(indices) => to_c_slice(indices.to_owned()),
+            Indices::U16(_) => CSlice::empty(),
+        },
+    }
+}
+
+fn to_c_border(border: Option<&Border>) -> CBorder {
+    let border = border.cloned().unwrap_or_default();
+    CBorder {
+        left: border.left,
+        top: border.top,
+        right: border.right,
+        bottom: border.bottom,
+    }
+}
+
+fn to_c_path(path: &[Vertex]) -> CSlice<CVertex> {
+    to_c_slice(path.iter().map(to_c_vertex).collect())
+}
+
+fn to_c_quantized(quantized: Option<&Quantized>) -> CQuantized {
+    let Some(quantized) = quantized else {
+        return CQuantized {
+            origin: CVertex { x: 0.0, y: 0.0 },
+            vertices: CSlice::empty(),
+            uvs: CSlice::empty(),
+        };
+    };
+    CQuantized {
+        origin: to_c_vertex(&quantized.origin),
+        vertices: to_c_slice(quantized.vertices.concat()),
+        uvs: to_c_slice(quantized.uvs.concat()),
+    }
+}
+
+fn to_c_indirection(indirection: Option<&Texture>) -> CTexture {
+    let Some(indirection) = indirection else {
+        return CTexture {
+            width: 0,
+            height: 0,
+            pixels: CSlice::empty(),
+        };
+    };
+    to_c_texture(indirection)
+}
+
+fn to_c_grid(grid: Option<&Grid>) -> CGrid {
+    let Some(grid) = grid else {
+        return CGrid {
+            columns: 0,
+            rows: 0,
+            cells: CSlice::empty(),
+        };
+    };
+    let cells = grid.cells.iter().map(|c| c.map_or(-1, |c| c as i64));
+    CGrid {
+        columns: grid.columns,
+        rows: grid.rows,
+        cells: to_c_slice(cells.collect()),
     }
 }
 
@@ -145,6 +318,13 @@ fn to_c_rect(rect: &Rect) -> CRect {
     }
 }
 
+fn to_c_instance(instance: &Instance) -> CInstance {
+    CInstance {
+        rect: to_c_rect(&instance.rect),
+        uv: instance.uv as u64,
+    }
+}
+
 fn to_c_pivot(p: &Pivot) -> CPivot {
     CPivot { x: p.x, y: p.y }
 }
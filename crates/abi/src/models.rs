@@ -8,6 +8,33 @@ pub struct CSourceSprite {
     pub texture: CTexture,
     pub has_pivot: bool,
     pub pivot: CPivot,
+    pub has_origin: bool,
+    pub origin: COrigin,
+    pub has_group: bool,
+    pub group: *const c_char,
+    pub has_ppu: bool,
+    pub ppu: f32,
+    pub has_border: bool,
+    pub border: CBorder,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CBorder {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct COrigin {
+    /// Index of the named anchor, from 0 (top-left) to 8 (bottom-right) in row-major order;
+    /// negative to use the pixel position instead.
+    pub anchor: i32,
+    pub x: f32,
+    pub y: f32,
 }
 
 #[repr(C)]
@@ -34,11 +61,31 @@ pub struct CPrefs {
     pub padding: u32,
     pub uv_inset: f32,
     pub trim_transparent: bool,
+    pub reuse_threshold: f32,
     pub atlas_size_limit: u32,
     pub atlas_square: bool,
     pub atlas_pot: bool,
+    pub atlas_uniform: bool,
+    pub atlas_balance: bool,
     pub ppu: f32,
     pub pivot: CPivot,
+    pub has_origin: bool,
+    pub origin: COrigin,
+    pub y_up: bool,
+    pub uv_bottom_left: bool,
+    pub clockwise: bool,
+    pub build_quads: bool,
+    pub weld_vertices: bool,
+    pub quantize: bool,
+    pub build_instances: bool,
+    pub merge_instance_uvs: bool,
+    pub build_indirection: bool,
+    pub build_outline: bool,
+    pub outline_threshold: u8,
+    pub outline_tolerance: f32,
+    pub lod_count: u32,
+    pub vertex_budget: u32,
+    pub alias_duplicates: bool,
     pub has_progress_callback: bool,
     pub progress_callback: unsafe extern "C" fn(CProgress),
 }
@@ -54,7 +101,17 @@ pub struct CResult {
 #[derive(Clone, Copy)]
 pub struct CArtifacts {
     pub atlases: CSlice<CTexture>,
+    /// Ratio of the occupied cells of each atlas, in the same order as the atlases.
+    pub occupancy: CSlice<f32>,
     pub sprites: CSlice<CDicedSprite>,
+    pub aliases: CSlice<CAlias>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CAlias {
+    pub id: *const c_char,
+    pub target: *const c_char,
 }
 
 #[repr(C)]
@@ -62,11 +119,75 @@ pub struct CArtifacts {
 pub struct CDicedSprite {
     pub id: *const c_char,
     pub atlas_index: u64,
+    /// Atlas index of each mesh face; empty when all the faces sample the atlas under index.
+    pub atlas_indices: CSlice<u64>,
+    pub layer: u64,
     pub vertices: CSlice<CVertex>,
     pub uvs: CSlice<CUv>,
-    pub indices: CSlice<u64>,
+    /// Populated when all the indices fit 16 bits; empty otherwise.
+    pub indices_u16: CSlice<u16>,
+    /// Populated when some of the indices don't fit 16 bits; empty otherwise.
+    pub indices_u32: CSlice<u32>,
     pub rect: CRect,
     pub pivot: CPivot,
+    pub has_group: bool,
+    pub group: *const c_char,
+    pub whole: bool,
+    pub has_indirection: bool,
+    pub indirection: CTexture,
+    pub has_grid: bool,
+    pub grid: CGrid,
+    pub instances: CSlice<CInstance>,
+    pub instance_uvs: CSlice<CRect>,
+    pub has_quantized: bool,
+    pub quantized: CQuantized,
+    /// Closed paths of the outline, each a slice of vertices.
+    pub outline: CSlice<CSlice<CVertex>>,
+    pub has_border: bool,
+    pub border: CBorder,
+    /// Nine-slice region (0-8) of each vertex; empty when the sprite has no border.
+    pub slices: CSlice<u8>,
+    /// Lower-detail mesh variants, from the most to the least detailed.
+    pub lods: CSlice<CLod>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CLod {
+    /// Atlas index of each mesh face; empty when all the faces sample the sprite atlas.
+    pub atlas_indices: CSlice<u64>,
+    pub vertices: CSlice<CVertex>,
+    pub uvs: CSlice<CUv>,
+    /// Populated when all the indices fit 16 bits; empty otherwise.
+    pub indices_u16: CSlice<u16>,
+    /// Populated when some of the indices don't fit 16 bits; empty otherwise.
+    pub indices_u32: CSlice<u32>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CQuantized {
+    pub origin: CVertex,
+    /// Vertex positions as consecutive X and Y pairs.
+    pub vertices: CSlice<i16>,
+    /// UVs as consecutive U and V pairs.
+    pub uvs: CSlice<u16>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CGrid {
+    pub columns: u32,
+    pub rows: u32,
+    /// Atlas cell indexes; -1 for the transparent units.
+    pub cells: CSlice<i64>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CInstance {
+    pub rect: CRect,
+    pub uv: u64,
 }
 
 #[repr(C)]
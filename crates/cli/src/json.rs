@@ -1,19 +1,62 @@
-use sprite_dicing::DicedSprite;
+use sprite_dicing::{Alias, Border, DicedSprite, Grid, Layout, Lod, Quantized};
 
-/// Serializes specified diced sprites to JSON string.
-pub fn sprites_to_json(sprites: &[DicedSprite]) -> String {
+/// Serializes specified diced sprites to JSON string; indirection textures are referenced
+/// by file names with specified extension.
+pub fn sprites_to_json(sprites: &[DicedSprite], ext: &str) -> String {
     let sprites = sprites
         .iter()
-        .map(sprite_to_json)
+        .enumerate()
+        .map(|(idx, sprite)| sprite_to_json(idx, sprite, ext))
         .collect::<Vec<_>>()
         .join(",");
 
     format!("[{sprites}\n]\n")
 }
 
-fn sprite_to_json(sprite: &DicedSprite) -> String {
-    let id = &sprite.id;
+/// Serializes specified sprite aliases to JSON string.
+pub fn aliases_to_json(aliases: &[Alias]) -> String {
+    let aliases = aliases
+        .iter()
+        .map(|a| {
+            let (id, target) = (escape(&a.id), escape(&a.target));
+            format!(r#"    {{ "id": "{id}", "target": "{target}" }}"#)
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    format!("[\n{aliases}\n]\n")
+}
+
+/// Name of the file to write indirection texture of the sprite with specified index.
+pub fn indirection_file_name(idx: usize, ext: &str) -> String {
+    format!("indirection_{idx}.{ext}")
+}
+
+/// Serializes group, dimensions and occupancy of specified atlas layouts to JSON string.
+pub fn atlases_to_json(layouts: &[Layout]) -> String {
+    let atlases = layouts
+        .iter()
+        .map(|l| {
+            let group = match &l.group {
+                Some(group) => format!(r#""{}""#, escape(group)),
+                None => "null".to_owned(),
+            };
+            let (width, height, occupancy) = (l.width, l.height, l.occupancy());
+            format!(
+                r#"    {{ "group": {group}, "width": {width}, "height": {height}, "occupancy": {occupancy} }}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    format!("[\n{atlases}\n]\n")
+}
+
+fn sprite_to_json(idx: usize, sprite: &DicedSprite, ext: &str) -> String {
+    let id = escape(&sprite.id);
     let atlas = sprite.atlas_index;
+    let atlas_indices = join_indices(&sprite.atlas_indices);
+    let layer = sprite.layer;
     let vertices = sprite
         .vertices
         .iter()
@@ -36,24 +79,234 @@ fn sprite_to_json(sprite: &DicedSprite) -> String {
     let y = sprite.rect.y;
     let width = sprite.rect.width;
     let height = sprite.rect.height;
+    let group = match &sprite.group {
+        Some(group) => format!(r#""{}""#, escape(group)),
+        None => "null".to_owned(),
+    };
+    let whole = sprite.whole;
+    let indirection = match &sprite.indirection {
+        Some(_) => format!(r#""{}""#, indirection_file_name(idx, ext)),
+        None => "null".to_owned(),
+    };
+    let instances = sprite
+        .instances
+        .iter()
+        .map(|i| {
+            let (x, y, width, height, uv) = (i.rect.x, i.rect.y, i.rect.width, i.rect.height, i.uv);
+            format!(r#"{{ "x": {x}, "y": {y}, "width": {width}, "height": {height}, "uv": {uv} }}"#)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let instance_uvs = sprite
+        .instance_uvs
+        .iter()
+        .map(|r| {
+            let (x, y, width, height) = (r.x, r.y, r.width, r.height);
+            format!(r#"{{ "x": {x}, "y": {y}, "width": {width}, "height": {height} }}"#)
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let quantized = match &sprite.quantized {
+        Some(quantized) => quantized_to_json(quantized),
+        None => "null".to_owned(),
+    };
+    let grid = match &sprite.grid {
+        Some(grid) => grid_to_json(grid),
+        None => "null".to_owned(),
+    };
+    let outline = sprite
+        .outline
+        .iter()
+        .map(|path| {
+            let path = path
+                .iter()
+                .map(|v| format!(r#"{{ "x": {}, "y": {} }}"#, v.x, v.y));
+            format!("[{}]", path.collect::<Vec<_>>().join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let border = match &sprite.border {
+        Some(border) => border_to_json(border),
+        None => "null".to_owned(),
+    };
+    let slices = sprite
+        .slices
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let lods = sprite
+        .lods
+        .iter()
+        .map(lod_to_json)
+        .collect::<Vec<_>>()
+        .join(", ");
 
     format!(
         r#"
     {{
         "id": "{id}",
         "atlas": {atlas},
+        "atlas_indices": [{atlas_indices}],
+        "layer": {layer},
         "vertices": [{vertices}],
         "uvs": [{uvs}],
         "indices": [{indices}],
-        "rect": {{ "x": {x}, "y": {y}, "width": {width}, "height": {height} }}
+        "rect": {{ "x": {x}, "y": {y}, "width": {width}, "height": {height} }},
+        "group": {group},
+        "whole": {whole},
+        "indirection": {indirection},
+        "grid": {grid},
+        "instances": [{instances}],
+        "instance_uvs": [{instance_uvs}],
+        "quantized": {quantized},
+        "outline": [{outline}],
+        "border": {border},
+        "slices": [{slices}],
+        "lods": [{lods}]
     }}"#
     )
 }
 
+fn lod_to_json(lod: &Lod) -> String {
+    let atlas_indices = join_indices(&lod.atlas_indices);
+    let vertices = lod
+        .vertices
+        .iter()
+        .map(|v| format!(r#"{{ "x": {}, "y": {} }}"#, v.x, v.y))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let uvs = lod
+        .uvs
+        .iter()
+        .map(|uv| format!(r#"{{ "u": {}, "v": {} }}"#, uv.u, uv.v))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let indices = lod
+        .indices
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"{{ "atlas_indices": [{atlas_indices}], "vertices": [{vertices}], "uvs": [{uvs}], "indices": [{indices}] }}"#
+    )
+}
+
+fn join_indices(indices: &[usize]) -> String {
+    let indices = indices.iter().map(|i| i.to_string());
+    indices.collect::<Vec<_>>().join(", ")
+}
+
+fn quantized_to_json(quantized: &Quantized) -> String {
+    let (x, y) = (quantized.origin.x, quantized.origin.y);
+    let pairs = |pairs: Vec<String>| pairs.join(", ");
+    let vertices = pairs(
+        quantized
+            .vertices
+            .iter()
+            .map(|[x, y]| format!("[{x}, {y}]"))
+            .collect(),
+    );
+    let uvs = pairs(
+        quantized
+            .uvs
+            .iter()
+            .map(|[u, v]| format!("[{u}, {v}]"))
+            .collect(),
+    );
+    format!(r#"{{ "origin": {{ "x": {x}, "y": {y} }}, "vertices": [{vertices}], "uvs": [{uvs}] }}"#)
+}
+
+fn border_to_json(border: &Border) -> String {
+    let (left, top, right, bottom) = (border.left, border.top, border.right, border.bottom);
+    format!(r#"{{ "left": {left}, "top": {top}, "right": {right}, "bottom": {bottom} }}"#)
+}
+
+fn grid_to_json(grid: &Grid) -> String {
+    let columns = grid.columns;
+    let rows = grid.rows;
+    let cells = grid
+        .cells
+        .iter()
+        .map(|c| c.map_or("null".to_owned(), |c| c.to_string()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(r#"{{ "columns": {columns}, "rows": {rows}, "cells": [{cells}] }}"#)
+}
+
+/// Escapes specified string to be inserted between quotes of a JSON string.
+fn escape(str: &str) -> String {
+    let mut escaped = String::with_capacity(str.len());
+    for char in str.chars() {
+        match char {
+            '"' => escaped.push_str(r#"\""#),
+            '\\' => escaped.push_str(r"\\"),
+            '\n' => escaped.push_str(r"\n"),
+            '\r' => escaped.push_str(r"\r"),
+            '\t' => escaped.push_str(r"\t"),
+            c if c.is_control() => escaped.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sprite_dicing::{Pivot, Rect, Uv, Vertex};
+    use sprite_dicing::{Indices, Instance, Pivot, Pixel, Rect, Texture, Uv, Vertex};
+    use std::collections::HashMap;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(escape("foo/bar"), "foo/bar");
+        assert_eq!(escape(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape("a\nb\u{1}"), r"a\nb\u0001");
+    }
+
+    #[test]
+    fn builds_aliases_json() {
+        let aliases = [
+            Alias {
+                id: "foo/idle_2".to_owned(),
+                target: "foo/idle_1".to_owned(),
+            },
+            Alias {
+                id: r#"bar"1"#.to_owned(),
+                target: "baz".to_owned(),
+            },
+        ];
+        assert_eq!(
+            aliases_to_json(&aliases),
+            r#"[
+    { "id": "foo/idle_2", "target": "foo/idle_1" },
+    { "id": "bar\"1", "target": "baz" }
+]
+"#
+        );
+    }
+
+    #[test]
+    fn builds_atlases_json() {
+        let layout = |group: Option<&str>, cells: u32| Layout {
+            group: group.map(str::to_owned),
+            width: 2,
+            height: 2,
+            columns: 2,
+            rows: 2,
+            cells: (0..cells).map(|c| (c as u64, c)).collect(),
+            spans: HashMap::new(),
+        };
+        assert_eq!(
+            atlases_to_json(&[layout(None, 4), layout(Some("foo"), 1)]),
+            r#"[
+    { "group": null, "width": 2, "height": 2, "occupancy": 1 },
+    { "group": "foo", "width": 2, "height": 2, "occupancy": 0.25 }
+]
+"#
+        );
+    }
 
     #[test]
     fn builds_sprites_json() {
@@ -61,40 +314,134 @@ mod tests {
             DicedSprite {
                 id: "foo/bar/img".to_owned(),
                 atlas_index: 0,
+                atlas_indices: vec![],
+                layer: 0,
                 vertices: vec![Vertex::new(1.0, -2.0), Vertex::new(-3.0, 4.525)],
                 uvs: vec![Uv::new(0.1, 0.2), Uv::new(0.3, 0.4)],
-                indices: vec![1, 2, 3],
+                indices: Indices::U16(vec![1, 2, 3]),
                 rect: Rect::new(0.5, 0.5, 100.0, 50.0),
                 pivot: Pivot::new(0.0, 0.0),
+                group: None,
+                whole: false,
+                indirection: None,
+                grid: Some(Grid {
+                    columns: 2,
+                    rows: 1,
+                    cells: vec![Some(3), None],
+                }),
+                instances: vec![],
+                instance_uvs: vec![],
+                quantized: Some(Quantized {
+                    origin: Vertex::new(0.5, 1.5),
+                    vertices: vec![[0, -1], [2, 3]],
+                    uvs: vec![[0, 65535]],
+                }),
+                outline: vec![
+                    vec![
+                        Vertex::new(0.0, 0.0),
+                        Vertex::new(0.0, 1.0),
+                        Vertex::new(1.0, 0.5),
+                    ],
+                    vec![
+                        Vertex::new(0.25, 0.5),
+                        Vertex::new(0.5, 0.5),
+                        Vertex::new(0.5, 0.25),
+                    ],
+                ],
+                border: None,
+                slices: vec![],
+                lods: vec![Lod {
+                    atlas_indices: vec![],
+                    vertices: vec![Vertex::new(1.0, -2.0)],
+                    uvs: vec![Uv::new(0.5, 0.25)],
+                    indices: Indices::U16(vec![0]),
+                }],
             },
             DicedSprite {
                 id: "img".to_owned(),
                 atlas_index: 1,
+                atlas_indices: vec![1, 0],
+                layer: 1,
                 vertices: vec![Vertex::new(-1.0, 2.0)],
                 uvs: vec![Uv::new(0.01, 0.02)],
-                indices: vec![0],
+                indices: Indices::U16(vec![0]),
                 rect: Rect::new(-1.5, 0.0, 0.0, 10.10),
                 pivot: Pivot::new(0.0, 0.0),
+                group: Some("foo".to_owned()),
+                whole: true,
+                indirection: Some(Texture {
+                    width: 1,
+                    height: 1,
+                    pixels: vec![Pixel::new(0, 0, 0, 255)],
+                }),
+                grid: None,
+                instances: vec![
+                    Instance {
+                        rect: Rect::new(-1.0, 2.0, 1.0, 1.0),
+                        uv: 0,
+                    },
+                    Instance {
+                        rect: Rect::new(0.0, 2.0, 0.5, 1.0),
+                        uv: 0,
+                    },
+                ],
+                instance_uvs: vec![Rect::new(0.01, 0.02, 0.5, 0.5)],
+                quantized: None,
+                outline: vec![],
+                border: Some(Border {
+                    left: 1,
+                    top: 2,
+                    right: 3,
+                    bottom: 4,
+                }),
+                slices: vec![4],
+                lods: vec![],
             },
         ];
         assert_eq!(
-            sprites_to_json(&sprites),
+            sprites_to_json(&sprites, "png"),
             r#"[
     {
         "id": "foo/bar/img",
         "atlas": 0,
+        "atlas_indices": [],
+        "layer": 0,
         "vertices": [{ "x": 1, "y": -2 }, { "x": -3, "y": 4.525 }],
         "uvs": [{ "u": 0.1, "v": 0.2 }, { "u": 0.3, "v": 0.4 }],
         "indices": [1, 2, 3],
-        "rect": { "x": 0.5, "y": 0.5, "width": 100, "height": 50 }
+        "rect": { "x": 0.5, "y": 0.5, "width": 100, "height": 50 },
+        "group": null,
+        "whole": false,
+        "indirection": null,
+        "grid": { "columns": 2, "rows": 1, "cells": [3, null] },
+        "instances": [],
+        "instance_uvs": [],
+        "quantized": { "origin": { "x": 0.5, "y": 1.5 }, "vertices": [[0, -1], [2, 3]], "uvs": [[0, 65535]] },
+        "outline": [[{ "x": 0, "y": 0 }, { "x": 0, "y": 1 }, { "x": 1, "y": 0.5 }], [{ "x": 0.25, "y": 0.5 }, { "x": 0.5, "y": 0.5 }, { "x": 0.5, "y": 0.25 }]],
+        "border": null,
+        "slices": [],
+        "lods": [{ "atlas_indices": [], "vertices": [{ "x": 1, "y": -2 }], "uvs": [{ "u": 0.5, "v": 0.25 }], "indices": [0] }]
     },
     {
         "id": "img",
         "atlas": 1,
+        "atlas_indices": [1, 0],
+        "layer": 1,
         "vertices": [{ "x": -1, "y": 2 }],
         "uvs": [{ "u": 0.01, "v": 0.02 }],
         "indices": [0],
-        "rect": { "x": -1.5, "y": 0, "width": 0, "height": 10.1 }
+        "rect": { "x": -1.5, "y": 0, "width": 0, "height": 10.1 },
+        "group": "foo",
+        "whole": true,
+        "indirection": "indirection_1.png",
+        "grid": null,
+        "instances": [{ "x": -1, "y": 2, "width": 1, "height": 1, "uv": 0 }, { "x": 0, "y": 2, "width": 0.5, "height": 1, "uv": 0 }],
+        "instance_uvs": [{ "x": 0.01, "y": 0.02, "width": 0.5, "height": 0.5 }],
+        "quantized": null,
+        "outline": [],
+        "border": { "left": 1, "top": 2, "right": 3, "bottom": 4 },
+        "slices": [4],
+        "lods": []
     }
 ]
 "#
@@ -4,8 +4,8 @@ pub mod models;
 
 use crate::models::*;
 use rayon::prelude::*;
-use sprite_dicing::{DicedSprite, Prefs, Progress, SourceSprite, Texture};
-use std::{fs, path::Path, path::PathBuf};
+use sprite_dicing::{Alias, DicedSprite, Layout, Prefs, Progress, SourceSprite, Texture};
+use std::{collections::HashSet, fs, path::Path, path::PathBuf};
 
 /// Packs all the textures of supported formats inside directory with specified path and
 /// writes generated atlas textures and diced sprite meshes serialized in JSON.
@@ -19,11 +19,26 @@ use std::{fs, path::Path, path::PathBuf};
 /// returns: [Ok] when operation successful, [Error] otherwise.
 pub fn dice_dir(dir: &Path, fs_prefs: &FsPrefs, prefs: &Prefs) -> Result<()> {
     let paths = collect_sources(dir, fs_prefs)?;
-    let sources = load_sources(dir, &paths, prefs, fs_prefs)?;
-    let diced = sprite_dicing::dice(&sources, prefs).map_err(Error::Dicing)?;
+    check_ids_unique(dir, &paths, fs_prefs)?;
+    let sources = stream_sources(dir, &paths, prefs, fs_prefs);
+    let diced = sprite_dicing::dice_stream(sources, prefs)?;
     let out_dir = fs_prefs.out.as_deref().unwrap_or(dir);
-    write_atlases(diced.atlases, out_dir, &fs_prefs.atlas_format, prefs)?;
-    write_sprites(diced.sprites, out_dir)
+    let fmt = &fs_prefs.atlas_format;
+    let mut sprites = diced.sprites;
+    write_layouts(&diced.layouts, out_dir)?;
+    if fs_prefs.atlas_array {
+        let (arrays, indexes) = stack_atlases(diced.atlases, &diced.layouts)?;
+        remap_atlas_indexes(&mut sprites, &indexes);
+        write_arrays(arrays, out_dir, fmt, prefs)?;
+    } else {
+        write_atlases(diced.atlases, out_dir, fmt, prefs)?;
+    }
+    write_sprites(&sprites, out_dir, fmt)?;
+    write_indirections(sprites, out_dir, fmt, prefs)?;
+    match diced.aliases.is_empty() {
+        true => Ok(()),
+        false => write_aliases(diced.aliases, out_dir),
+    }
 }
 
 fn collect_sources(dir: &Path, prefs: &FsPrefs) -> Result<Vec<PathBuf>> {
@@ -39,25 +54,56 @@ fn collect_sources(dir: &Path, prefs: &FsPrefs) -> Result<Vec<PathBuf>> {
     Ok(sprites)
 }
 
-fn load_sources(
-    root: &Path,
-    paths: &[PathBuf],
-    prefs: &Prefs,
-    fs_prefs: &FsPrefs,
-) -> Result<Vec<SourceSprite>> {
-    let mut sprites = Vec::with_capacity(paths.len());
-    for (idx, path) in paths.iter().enumerate() {
-        Progress::report(prefs, 0, idx, paths.len(), "Decoding source textures");
-        sprites.push(create_sprite(root, path, fs_prefs)?);
+fn check_ids_unique(root: &Path, paths: &[PathBuf], prefs: &FsPrefs) -> Result<()> {
+    let mut ids = HashSet::with_capacity(paths.len());
+    for path in paths {
+        let (id, _) = eval_sprite_id_and_ppu(root, path, prefs);
+        if !ids.insert(id.clone()) {
+            return Err(Error::DuplicateId(id));
+        }
     }
-    Ok(sprites)
+    Ok(())
+}
+
+/// Lazily decodes source textures, so that only one is held in memory at a time while dicing.
+fn stream_sources<'a>(
+    root: &'a Path,
+    paths: &'a [PathBuf],
+    prefs: &'a Prefs,
+    fs_prefs: &'a FsPrefs,
+) -> impl Iterator<Item = Result<SourceSprite>> + 'a {
+    paths.iter().enumerate().map(move |(idx, path)| {
+        Progress::report(prefs, 0, idx, paths.len(), "Decoding source textures");
+        create_sprite(root, path, fs_prefs)
+    })
 }
 
 fn create_sprite(root: &Path, path: &Path, prefs: &FsPrefs) -> Result<SourceSprite> {
-    let id = eval_sprite_id(root, path, &prefs.separator);
+    let (id, ppu) = eval_sprite_id_and_ppu(root, path, prefs);
     let texture = img::to_texture(&img::load(path)?);
     let pivot = None;
-    Ok(SourceSprite { id, texture, pivot })
+    let origin = None;
+    let group = match prefs.group {
+        true => eval_sprite_group(root, path, &prefs.separator),
+        false => None,
+    };
+    Ok(SourceSprite {
+        id,
+        texture,
+        pivot,
+        origin,
+        group,
+        ppu,
+        border: prefs.border.to_owned(),
+    })
+}
+
+fn eval_sprite_id_and_ppu(root: &Path, path: &Path, prefs: &FsPrefs) -> (String, Option<f32>) {
+    let id = eval_sprite_id(root, path, &prefs.separator);
+    match prefs.ppu_suffix {
+        true => split_ppu_suffix(id),
+        false => (id, None),
+    }
 }
 
 fn eval_sprite_id(root: &Path, path: &Path, separator: &str) -> String {
@@ -69,6 +115,28 @@ fn eval_sprite_id(root: &Path, path: &Path, separator: &str) -> String {
         .join(separator)
 }
 
+/// Splits PPU specified after last '@' of the sprite ID, when the remainder is a finite
+/// positive number.
+fn split_ppu_suffix(id: String) -> (String, Option<f32>) {
+    match id
+        .rsplit_once('@')
+        .map(|(id, ppu)| (id, ppu.parse::<f32>()))
+    {
+        Some((id, Ok(ppu))) if ppu.is_finite() && ppu > 0.0 => (id.to_owned(), Some(ppu)),
+        _ => (id, None),
+    }
+}
+
+fn eval_sprite_group(root: &Path, path: &Path, separator: &str) -> Option<String> {
+    let dirs = path
+        .parent()?
+        .iter()
+        .skip(root.iter().count())
+        .map(|o| o.to_str().unwrap_or(""))
+        .collect::<Vec<_>>();
+    (!dirs.is_empty()).then(|| dirs.join(separator))
+}
+
 fn write_atlases(tex: Vec<Texture>, dir: &Path, fmt: &AtlasFormat, prefs: &Prefs) -> Result<()> {
     Progress::report(prefs, 4, 0, tex.len(), "Encoding atlas textures");
     tex.into_par_iter().enumerate().try_for_each(|(idx, tex)| {
@@ -77,20 +145,107 @@ fn write_atlases(tex: Vec<Texture>, dir: &Path, fmt: &AtlasFormat, prefs: &Prefs
     })
 }
 
+fn write_arrays(tex: Vec<Texture>, dir: &Path, fmt: &AtlasFormat, prefs: &Prefs) -> Result<()> {
+    Progress::report(prefs, 4, 0, tex.len(), "Encoding atlas arrays");
+    tex.into_par_iter().enumerate().try_for_each(|(idx, tex)| {
+        let name = format!("atlas_array_{idx}.{}", fmt.extension());
+        write_atlas(&dir.join(name), tex)
+    })
+}
+
+/// Stacks atlases of each group vertically, in order of the layers. Returns the stacked
+/// arrays, in order the groups first appear, along with index of the array each atlas is
+/// stacked into.
+fn stack_atlases(atlases: Vec<Texture>, layouts: &[Layout]) -> Result<(Vec<Texture>, Vec<usize>)> {
+    let mut arrays: Vec<Texture> = vec![];
+    // Group and page height of each array.
+    let mut keys: Vec<(&Option<String>, u32)> = vec![];
+    let mut indexes = Vec::with_capacity(atlases.len());
+    for (atlas, layout) in atlases.into_iter().zip(layouts) {
+        match keys.iter().position(|(group, _)| *group == &layout.group) {
+            Some(idx) => {
+                let array = &mut arrays[idx];
+                if array.width != atlas.width || keys[idx].1 != atlas.height {
+                    return Err(Error::NonUniformArray);
+                }
+                array.height += atlas.height;
+                array.pixels.extend(atlas.pixels);
+                indexes.push(idx);
+            }
+            None => {
+                keys.push((&layout.group, atlas.height));
+                indexes.push(arrays.len());
+                arrays.push(atlas);
+            }
+        }
+    }
+    Ok((arrays, indexes))
+}
+
+/// Replaces atlas indexes of the sprites, their faces and LOD faces with the specified
+/// indexes of the arrays the atlases are stacked into.
+fn remap_atlas_indexes(sprites: &mut [DicedSprite], indexes: &[usize]) {
+    for sprite in sprites.iter_mut() {
+        sprite.atlas_index = indexes[sprite.atlas_index];
+        for idx in sprite.atlas_indices.iter_mut() {
+            *idx = indexes[*idx];
+        }
+        for lod in sprite.lods.iter_mut() {
+            for idx in lod.atlas_indices.iter_mut() {
+                *idx = indexes[*idx];
+            }
+        }
+    }
+}
+
 fn write_atlas(path: &Path, tex: Texture) -> Result<()> {
     let img = img::from_texture(tex)?;
     img::save(path, img)
 }
 
-fn write_sprites(sprites: Vec<DicedSprite>, dir: &Path) -> Result<()> {
-    let json = json::sprites_to_json(&sprites);
+fn write_layouts(layouts: &[Layout], dir: &Path) -> Result<()> {
+    let json = json::atlases_to_json(layouts);
+    let path = dir.join("atlases.json");
+    fs::write(path, json).map_err(Error::Io)
+}
+
+fn write_sprites(sprites: &[DicedSprite], dir: &Path, fmt: &AtlasFormat) -> Result<()> {
+    let json = json::sprites_to_json(sprites, fmt.extension());
     let path = dir.join("sprites.json");
     fs::write(path, json).map_err(Error::Io)
 }
 
+/// Writes indirection textures of the sprites, named after index of the sprite.
+fn write_indirections(
+    sprites: Vec<DicedSprite>,
+    dir: &Path,
+    fmt: &AtlasFormat,
+    prefs: &Prefs,
+) -> Result<()> {
+    let tex = sprites.into_iter().enumerate();
+    let tex = tex.filter_map(|(idx, s)| s.indirection.map(|t| (idx, t)));
+    let tex = tex.collect::<Vec<_>>();
+    if tex.is_empty() {
+        return Ok(());
+    }
+    Progress::report(prefs, 4, 0, tex.len(), "Encoding indirection textures");
+    tex.into_par_iter().try_for_each(|(idx, tex)| {
+        let name = json::indirection_file_name(idx, fmt.extension());
+        write_atlas(&dir.join(name), tex)
+    })
+}
+
+fn write_aliases(aliases: Vec<Alias>, dir: &Path) -> Result<()> {
+    let json = json::aliases_to_json(&aliases);
+    let path = dir.join("aliases.json");
+    fs::write(path, json).map_err(Error::Io)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use sprite_dicing::{Indices, Lod, Pivot, Pixel, Rect};
+    use std::collections::HashMap;
 
     #[test]
     fn evaluates_sprite_id_from_path() {
@@ -107,4 +262,133 @@ mod tests {
             "foo/bar/img"
         );
     }
+
+    #[test]
+    fn evaluates_sprite_group_from_path() {
+        assert_eq!(
+            eval_sprite_group(Path::new("/foo/bar"), Path::new("/foo/bar/img.png"), "/"),
+            None
+        );
+        assert_eq!(
+            eval_sprite_group(Path::new("/foo"), Path::new("/foo/bar/img.png"), "/"),
+            Some("bar".to_owned())
+        );
+        assert_eq!(
+            eval_sprite_group(Path::new("/"), Path::new("/foo/bar/img.png"), "."),
+            Some("foo.bar".to_owned())
+        );
+    }
+
+    #[test]
+    fn splits_ppu_suffix_from_sprite_id() {
+        assert_eq!(
+            split_ppu_suffix("img@200".into()),
+            ("img".into(), Some(200.0))
+        );
+        assert_eq!(
+            split_ppu_suffix("a@b/img@0.5".into()),
+            ("a@b/img".into(), Some(0.5))
+        );
+        assert_eq!(
+            split_ppu_suffix("dir@2/img".into()),
+            ("dir@2/img".into(), None)
+        );
+        assert_eq!(split_ppu_suffix("img".into()), ("img".into(), None));
+        for invalid in ["img@inf", "img@nan", "img@-5", "img@0"] {
+            assert_eq!(split_ppu_suffix(invalid.into()), (invalid.into(), None));
+        }
+    }
+
+    #[test]
+    fn stacks_atlases_per_group() {
+        let atlases = vec![tex(1, 1), tex(1, 1), tex(2, 1)];
+        let layouts = [layout(None), layout(None), layout(Some("foo"))];
+        let (arrays, indexes) = stack_atlases(atlases, &layouts).unwrap();
+        assert_eq!(arrays.len(), 2);
+        assert_eq!((arrays[0].width, arrays[0].height), (1, 2));
+        assert_eq!((arrays[1].width, arrays[1].height), (2, 1));
+        assert_eq!(indexes, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn stacks_non_contiguous_atlases_of_group() {
+        let atlases = vec![tex(1, 1), tex(2, 1), tex(1, 1)];
+        let layouts = [layout(None), layout(Some("foo")), layout(None)];
+        let (arrays, indexes) = stack_atlases(atlases, &layouts).unwrap();
+        assert_eq!((arrays[0].width, arrays[0].height), (1, 2));
+        assert_eq!(indexes, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn errs_when_stacked_atlases_are_not_uniform() {
+        let atlases = vec![tex(1, 1), tex(2, 2)];
+        let layouts = [layout(None), layout(None)];
+        assert!(
+            stack_atlases(atlases, &layouts)
+                .is_err_and(|e| e.to_string() == "Atlas array requires uniform atlases.")
+        );
+    }
+
+    #[test]
+    fn remaps_atlas_indexes_of_faces_spanning_atlases() {
+        let layouts = [layout(Some("foo")), layout(None), layout(Some("foo"))];
+        let atlases = vec![tex(1, 1), tex(1, 1), tex(1, 1)];
+        let (_, indexes) = stack_atlases(atlases, &layouts).unwrap();
+        let mut sprites = [sprite(2, vec![0, 0, 2, 2])];
+        remap_atlas_indexes(&mut sprites, &indexes);
+        assert_eq!(sprites[0].atlas_index, 0);
+        assert_eq!(sprites[0].atlas_indices, vec![0, 0, 0, 0]);
+        assert_eq!(sprites[0].lods[0].atlas_indices, vec![0, 0, 0, 0]);
+    }
+
+    fn sprite(atlas_index: usize, atlas_indices: Vec<usize>) -> DicedSprite {
+        DicedSprite {
+            id: "img".to_owned(),
+            atlas_index,
+            atlas_indices: atlas_indices.clone(),
+            layer: 1,
+            vertices: vec![],
+            uvs: vec![],
+            indices: Indices::U16(vec![]),
+            rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+            pivot: Pivot::new(0.0, 0.0),
+            group: Some("foo".to_owned()),
+            whole: false,
+            indirection: None,
+            grid: None,
+            instances: vec![],
+            instance_uvs: vec![],
+            quantized: None,
+            outline: vec![],
+            border: None,
+            slices: vec![],
+            lods: vec![Lod {
+                atlas_indices,
+                vertices: vec![],
+                uvs: vec![],
+                indices: Indices::U16(vec![]),
+            }],
+        }
+    }
+
+    fn tex(width: u32, height: u32) -> Texture {
+        let pixels = vec![Pixel::default(); (width * height) as usize];
+        Texture {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn layout(group: Option<&str>) -> Layout {
+        Layout {
+            group: group.map(str::to_owned),
+            width: 1,
+            height: 1,
+            columns: 1,
+            rows: 1,
+            cells: HashMap::new(),
+            spans: HashMap::new(),
+        }
+    }
 }
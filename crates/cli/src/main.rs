@@ -1,9 +1,10 @@
 //! Command line interface of the library.
 
 use clap::Parser;
+use clap_derive::ValueEnum;
 use cli::models::*;
 use indicatif::{ProgressBar, ProgressStyle};
-use sprite_dicing::{Pivot, Prefs};
+use sprite_dicing::{Anchor, Border, Origin, Pivot, Prefs};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -19,6 +20,12 @@ struct Args {
     /// When recursive, the separator to join ID of nested sprites.
     #[arg(long, default_value = "/")]
     separator: String,
+    /// When recursive, pack sprites of each directory into dedicated atlases.
+    #[arg(short, long, default_value_t = false)]
+    group: bool,
+    /// Write atlases of each group as a single texture with uniform pages stacked vertically.
+    #[arg(short, long, default_value_t = false)]
+    array: bool,
     /// Format of the generated atlas textures.
     #[arg(short, long, value_enum, default_value_t = AtlasFormat::Png)]
     format: AtlasFormat,
@@ -34,6 +41,9 @@ struct Args {
     /// Trim transparent areas on the built meshes.
     #[arg(short, long, default_value_t = false)]
     trim: bool,
+    /// Pack sprites with reuse ratio (0.0-1.0) below the threshold whole, instead of dicing.
+    #[arg(long, default_value_t = 0.0)]
+    reuse: f32,
     /// Maximum size of a single generated atlas texture.
     #[arg(short, long, default_value_t = 2048)]
     limit: u32,
@@ -43,12 +53,106 @@ struct Args {
     /// Force atlas size to always be power of two.
     #[arg(long, default_value_t = false)]
     pot: bool,
+    /// Force atlases of each group to have identical size.
+    #[arg(long, default_value_t = false)]
+    uniform: bool,
+    /// Distribute units evenly over the atlases instead of filling each up to the limit.
+    #[arg(long, default_value_t = false)]
+    balance: bool,
     /// Pixel per unit ratio of the diced sprite mesh vertices.
     #[arg(long, default_value_t = 100.0)]
     ppu: f32,
+    /// Read PPU of each sprite from '@' suffix of the file name, e.g. 'hero@200.png'.
+    #[arg(long, default_value_t = false)]
+    ppu_suffix: bool,
     /// Origin of the diced sprite mesh, in relative offsets from top-left corner.
     #[arg(long, num_args = 2, default_values_t = [0.5, 0.5])]
     pivot: Vec<f32>,
+    /// Origin of the diced sprite mesh, in pixels from top-left corner of the source texture;
+    /// unlike pivot, not affected by trimming.
+    #[arg(long, num_args = 2, conflicts_with = "anchor")]
+    origin: Option<Vec<f32>>,
+    /// Origin of the diced sprite mesh, as named point of the source texture; unlike pivot,
+    /// not affected by trimming.
+    #[arg(long, value_enum)]
+    anchor: Option<AnchorArg>,
+    /// Nine-slice borders of the sprites, in pixels from the left, top, right and bottom edges.
+    #[arg(long, num_args = 4)]
+    border: Option<Vec<u32>>,
+    /// Point Y axis of the mesh vertices up; pivot is then relative to bottom-left corner.
+    #[arg(long, default_value_t = false)]
+    y_up: bool,
+    /// Place origin of the atlas texture coordinates at bottom-left corner.
+    #[arg(long, default_value_t = false)]
+    uv_bottom_left: bool,
+    /// Wind the mesh faces clockwise.
+    #[arg(long, default_value_t = false)]
+    clockwise: bool,
+    /// Don't build mesh quads of the diced sprites; requires instances.
+    #[arg(long, default_value_t = false)]
+    no_quads: bool,
+    /// Merge the mesh vertices with identical positions and UVs.
+    #[arg(long, default_value_t = false)]
+    weld: bool,
+    /// Build quantized integer vertices and UVs of the diced sprites.
+    #[arg(long, default_value_t = false)]
+    quantize: bool,
+    /// Build per-unit instance records of the diced sprites.
+    #[arg(long, default_value_t = false)]
+    instances: bool,
+    /// Share identical UV rects between the instances.
+    #[arg(long, default_value_t = false)]
+    merge_uvs: bool,
+    /// Build indirection textures for rendering the diced sprites as single quads.
+    #[arg(long, default_value_t = false)]
+    indirection: bool,
+    /// Build outline polygons enclosing opaque regions of the diced sprites.
+    #[arg(long, default_value_t = false)]
+    outline: bool,
+    /// Minimum alpha (0-255) of a pixel to be enclosed by the outline.
+    #[arg(long, default_value_t = 128)]
+    outline_threshold: u8,
+    /// Maximum deviation of the simplified outline from the pixel borders, in pixels.
+    #[arg(long, default_value_t = 1.0)]
+    outline_tolerance: f32,
+    /// Number of lower-detail mesh variants to build for each diced sprite.
+    #[arg(long, default_value_t = 0)]
+    lods: u32,
+    /// Maximum number of mesh vertices per sprite; sprites over the budget are diced with larger units.
+    #[arg(long, default_value_t = 0)]
+    vertex_budget: u32,
+    /// Write sprites identical to others as aliases instead of duplicating their meshes.
+    #[arg(long, default_value_t = false)]
+    alias: bool,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum AnchorArg {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    MiddleLeft,
+    Center,
+    MiddleRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl From<AnchorArg> for Anchor {
+    fn from(arg: AnchorArg) -> Self {
+        match arg {
+            AnchorArg::TopLeft => Anchor::TopLeft,
+            AnchorArg::TopCenter => Anchor::TopCenter,
+            AnchorArg::TopRight => Anchor::TopRight,
+            AnchorArg::MiddleLeft => Anchor::MiddleLeft,
+            AnchorArg::Center => Anchor::Center,
+            AnchorArg::MiddleRight => Anchor::MiddleRight,
+            AnchorArg::BottomLeft => Anchor::BottomLeft,
+            AnchorArg::BottomCenter => Anchor::BottomCenter,
+            AnchorArg::BottomRight => Anchor::BottomRight,
+        }
+    }
 }
 
 fn main() -> Result<()> {
@@ -62,18 +166,50 @@ fn main() -> Result<()> {
         out: args.out,
         recursive: args.recursive,
         separator: args.separator,
+        group: args.group,
+        atlas_array: args.array,
         atlas_format: args.format,
+        ppu_suffix: args.ppu_suffix,
+        border: args.border.map(|b| Border {
+            left: b[0],
+            top: b[1],
+            right: b[2],
+            bottom: b[3],
+        }),
     };
     let prefs = Prefs {
         unit_size: args.size,
         padding: args.pad,
         uv_inset: args.inset,
         trim_transparent: args.trim,
+        reuse_threshold: args.reuse,
         atlas_size_limit: args.limit,
         atlas_square: args.square,
         atlas_pot: args.pot,
+        atlas_uniform: args.uniform || args.array,
+        atlas_balance: args.balance,
         ppu: args.ppu,
         pivot: Pivot::new(args.pivot[0], args.pivot[1]),
+        origin: match (args.origin, args.anchor) {
+            (Some(origin), _) => Some(Origin::Pixel(origin[0], origin[1])),
+            (None, Some(anchor)) => Some(Origin::Anchor(anchor.into())),
+            (None, None) => None,
+        },
+        y_up: args.y_up,
+        uv_bottom_left: args.uv_bottom_left,
+        clockwise: args.clockwise,
+        build_quads: !args.no_quads,
+        weld_vertices: args.weld,
+        quantize: args.quantize,
+        build_instances: args.instances,
+        merge_instance_uvs: args.merge_uvs,
+        build_indirection: args.indirection,
+        build_outline: args.outline,
+        outline_threshold: args.outline_threshold,
+        outline_tolerance: args.outline_tolerance,
+        lod_count: args.lods,
+        vertex_budget: args.vertex_budget,
+        alias_duplicates: args.alias,
         on_progress: Some(Box::new(move |p| {
             bar.set_position((p.ratio * 100.0) as u64);
             bar.set_message(p.activity);
@@ -1,4 +1,5 @@
 use clap_derive::ValueEnum;
+use sprite_dicing::Border;
 use std::path::PathBuf;
 
 /// Result of a dicing operation executed via CLI.
@@ -13,6 +14,10 @@ pub enum Error {
     Image(image::ImageError),
     /// An issue with an I/O operation.
     Io(std::io::Error),
+    /// Multiple source textures map to the sprite ID.
+    DuplicateId(String),
+    /// Atlases stacked into an array have different dimensions.
+    NonUniformArray,
 }
 
 impl std::fmt::Display for Error {
@@ -21,10 +26,20 @@ impl std::fmt::Display for Error {
             Error::Dicing(info) => write!(f, "{info}"),
             Error::Image(err) => write!(f, "{err}"),
             Error::Io(err) => write!(f, "{err}"),
+            Error::DuplicateId(id) => {
+                write!(f, "Multiple source textures map to sprite ID '{id}'.")
+            }
+            Error::NonUniformArray => write!(f, "Atlas array requires uniform atlases."),
         }
     }
 }
 
+impl From<sprite_dicing::Error> for Error {
+    fn from(err: sprite_dicing::Error) -> Self {
+        Error::Dicing(err)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Error::Io(err)
@@ -48,8 +63,19 @@ pub struct FsPrefs {
     pub recursive: bool,
     /// When recursive enabled, will use the separator when building sprite IDs; '/' by default.
     pub separator: String,
+    /// When recursive, will pack sprites of each directory into dedicated atlases; false by default.
+    pub group: bool,
+    /// Whether to write atlases of each group as a single texture with the pages stacked
+    /// vertically, ready to be imported as texture array; requires uniform atlases. Atlas index
+    /// of the written sprites is then the index of the array texture.
+    pub atlas_array: bool,
     /// Format to encode generated atlas textures into.
     pub atlas_format: AtlasFormat,
+    /// Whether to read PPU of each sprite from '@' suffix of the texture file name, e.g.
+    /// 'hero@200.png'; the suffix is excluded from the sprite ID; false by default.
+    pub ppu_suffix: bool,
+    /// Nine-slice borders to apply to all the source sprites; none by default.
+    pub border: Option<Border>,
 }
 
 impl Default for FsPrefs {
@@ -58,7 +84,11 @@ impl Default for FsPrefs {
             out: None,
             recursive: false,
             separator: "/".to_owned(),
+            group: false,
+            atlas_array: false,
             atlas_format: AtlasFormat::Png,
+            ppu_suffix: false,
+            border: None,
         }
     }
 }
@@ -0,0 +1,210 @@
+use crate::models::*;
+
+/// Writes meshes of the specified sprites into interleaved vertex buffer with specified layout.
+pub(crate) fn write(sprites: &[DicedSprite], layout: &VertexLayout) -> Result<VertexBuffer> {
+    validate(layout)?;
+
+    let vertex_count = sprites.iter().map(|s| s.vertices.len()).sum::<usize>();
+    let mut vertices = vec![0; vertex_count * layout.stride];
+    let mut indices = vec![];
+    let mut ranges = Vec::with_capacity(sprites.len());
+    let mut first_vertex = 0;
+    for sprite in sprites {
+        ranges.push(BufferRange {
+            first_vertex,
+            vertex_count: sprite.vertices.len(),
+            first_index: indices.len(),
+            index_count: sprite.indices.len(),
+        });
+        for idx in 0..sprite.vertices.len() {
+            let start = (first_vertex + idx) * layout.stride;
+            let vertex = &mut vertices[start..start + layout.stride];
+            for element in layout.elements.iter() {
+                let values = match element.attribute {
+                    VertexAttribute::Position => [sprite.vertices[idx].x, sprite.vertices[idx].y],
+                    VertexAttribute::Uv => [sprite.uvs[idx].u, sprite.uvs[idx].v],
+                };
+                let bytes = &mut vertex[element.offset..element.offset + element.format.size()];
+                write_element(bytes, element.format, values);
+            }
+        }
+        indices.extend(sprite.indices.iter().map(|i| i + first_vertex));
+        first_vertex += sprite.vertices.len();
+    }
+
+    Ok(VertexBuffer {
+        vertices,
+        indices: indices.try_into()?,
+        ranges,
+    })
+}
+
+fn validate(layout: &VertexLayout) -> Result<()> {
+    if layout.elements.is_empty() {
+        return Err(Error::Spec("Vertex layout doesn't have elements."));
+    }
+    for element in layout.elements.iter() {
+        if element.offset + element.format.size() > layout.stride {
+            return Err(Error::Spec("Vertex element exceeds layout stride."));
+        }
+        if element.attribute == VertexAttribute::Position && element.format == VertexFormat::Unorm16
+        {
+            return Err(Error::Spec("Vertex positions can't be normalized."));
+        }
+    }
+    Ok(())
+}
+
+fn write_element(bytes: &mut [u8], format: VertexFormat, values: [f32; 2]) {
+    for (idx, value) in values.into_iter().enumerate() {
+        match format {
+            VertexFormat::F32 => {
+                bytes[idx * 4..idx * 4 + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            VertexFormat::F16 => {
+                bytes[idx * 2..idx * 2 + 2].copy_from_slice(&to_f16(value).to_le_bytes());
+            }
+            VertexFormat::Unorm16 => {
+                let norm = (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+                bytes[idx * 2..idx * 2 + 2].copy_from_slice(&norm.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Converts specified value to bits of IEEE 754 half precision float, rounding to nearest even.
+fn to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+    if exp == 0xFF {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+    let exp = exp - 127 + 15;
+    if exp >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if exp <= 0 {
+        // Below the normal range: shifting the mantissa with the implicit bit to a subnormal.
+        if exp < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        let odd = (mantissa >> shift) & 1;
+        return sign | ((mantissa + (1 << (shift - 1)) - 1 + odd) >> shift) as u16;
+    }
+    let half = ((exp as u32) << 10) | (mantissa >> 13);
+    let rest = mantissa & 0x1FFF;
+    // Carry from the mantissa into the exponent is intended, rounding up to the next power.
+    let round = rest > 0x1000 || (rest == 0x1000 && half & 1 == 1);
+    sign | (half + round as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_f16;
+    use crate::fixtures::*;
+    use crate::models::*;
+
+    #[test]
+    fn errs_when_layout_is_empty() {
+        let layout = VertexLayout {
+            elements: vec![],
+            stride: 8,
+        };
+        assert!(write(&[sprite()], &layout).is_err());
+    }
+
+    #[test]
+    fn errs_when_element_exceeds_stride() {
+        let layout = layout(vec![(VertexAttribute::Uv, VertexFormat::F32, 4)], 8);
+        assert!(write(&[sprite()], &layout).is_err());
+    }
+
+    #[test]
+    fn errs_when_positions_normalized() {
+        let layout = layout(
+            vec![(VertexAttribute::Position, VertexFormat::Unorm16, 0)],
+            4,
+        );
+        assert!(write(&[sprite()], &layout).is_err());
+    }
+
+    #[test]
+    fn elements_are_written_at_offsets() {
+        let elements = vec![
+            (VertexAttribute::Uv, VertexFormat::Unorm16, 0),
+            (VertexAttribute::Position, VertexFormat::F32, 4),
+        ];
+        let buffer = write(&[sprite()], &layout(elements, 16)).unwrap();
+        assert_eq!(buffer.vertices.len(), 4 * 16);
+        let vertex = &buffer.vertices[16..32];
+        assert_eq!(vertex[0..4], [0, 0, 255, 255]);
+        assert_eq!(vertex[4..8], (-0.5_f32).to_le_bytes());
+        assert_eq!(vertex[8..12], 0.5_f32.to_le_bytes());
+        assert_eq!(vertex[12..16], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn batched_indices_are_offset() {
+        let elements = vec![(VertexAttribute::Position, VertexFormat::F16, 0)];
+        let buffer = write(&[sprite(), sprite()], &layout(elements, 4)).unwrap();
+        let indices: Vec<_> = buffer.indices.iter().collect();
+        assert_eq!(indices, vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+        assert_eq!(buffer.ranges[1].first_vertex, 4);
+        assert_eq!(buffer.ranges[1].first_index, 6);
+        assert_eq!(buffer.ranges[1].index_count, 6);
+    }
+
+    #[test]
+    fn f16_is_converted() {
+        assert_eq!(to_f16(0.0), 0x0000);
+        assert_eq!(to_f16(-0.0), 0x8000);
+        assert_eq!(to_f16(1.0), 0x3C00);
+        assert_eq!(to_f16(0.5), 0x3800);
+        assert_eq!(to_f16(-2.0), 0xC000);
+        assert_eq!(to_f16(0.1), 0x2E66);
+        assert_eq!(to_f16(65504.0), 0x7BFF);
+        assert_eq!(to_f16(1e6), 0x7C00);
+        assert_eq!(to_f16(f32::NAN) & 0x7E00, 0x7E00);
+        assert_eq!(to_f16(5.960_464_5e-8), 0x0001);
+        assert_eq!(to_f16(1e-9), 0x0000);
+    }
+
+    fn write(sprites: &[DicedSprite], layout: &VertexLayout) -> Result<VertexBuffer> {
+        super::write(sprites, layout)
+    }
+
+    fn layout(
+        elements: Vec<(VertexAttribute, VertexFormat, usize)>,
+        stride: usize,
+    ) -> VertexLayout {
+        let elements = elements
+            .into_iter()
+            .map(|(attribute, format, offset)| VertexElement {
+                attribute,
+                format,
+                offset,
+            });
+        VertexLayout {
+            elements: elements.collect(),
+            stride,
+        }
+    }
+
+    fn sprite() -> DicedSprite {
+        let sprites = [R1X1.sprite()];
+        let prefs = Prefs {
+            unit_size: 1,
+            padding: 0,
+            ppu: 1.0,
+            trim_transparent: false,
+            ..Prefs::default()
+        };
+        let mut diced = crate::dice(&sprites, &prefs).unwrap();
+        diced.sprites.pop().unwrap()
+    }
+}
@@ -6,15 +6,30 @@ pub(crate) fn build(packed: &[Atlas], prefs: &Prefs) -> Result<Vec<DicedSprite>>
     if prefs.ppu <= 0.0 {
         return Err(Error::Spec("PPU can't be zero or negative."));
     }
+    if !prefs.build_quads && !prefs.build_instances {
+        return Err(Error::Spec("Either quads or instances should be built."));
+    }
+    let mut textures = packed.iter().flat_map(|a| a.packed.iter());
+    if textures.any(|t| t.ppu.is_some_and(|ppu| ppu <= 0.0)) {
+        return Err(Error::Spec("Sprite PPU can't be zero or negative."));
+    }
+    let mut textures = packed.iter().flat_map(|a| a.packed.iter());
+    if textures.any(|t| t.border.as_ref().is_some_and(|b| !fits_border(b, &t.size))) {
+        return Err(Error::Spec("Sprite border exceeds texture dimensions."));
+    }
 
     let total = packed.iter().map(|a| a.packed.len()).sum();
     let mut sprites = Vec::with_capacity(total);
 
     for (atlas_idx, atlas) in packed.iter().enumerate() {
+        let layer = packed[..atlas_idx]
+            .iter()
+            .filter(|a| a.group == atlas.group);
+        let layer = layer.count();
         for diced_tex in atlas.packed.iter() {
             Progress::report(prefs, 3, sprites.len(), total, "Building diced sprites");
-            let ctx = new_ctx(atlas, atlas_idx, diced_tex, prefs);
-            sprites.push(build_it(ctx));
+            let ctx = new_ctx(packed, atlas_idx, layer, diced_tex, prefs);
+            sprites.push(build_it(ctx)?);
         }
     }
 
@@ -22,60 +37,458 @@ pub(crate) fn build(packed: &[Atlas], prefs: &Prefs) -> Result<Vec<DicedSprite>>
 }
 
 struct Context<'a> {
+    unit_size: u32,
+    padded_unit_size: u32,
     ppu: f32,
     trim: bool,
+    y_up: bool,
+    uv_bottom_left: bool,
+    clockwise: bool,
+    quads: bool,
+    weld: bool,
+    quantize: bool,
+    instances: bool,
+    merge_uvs: bool,
+    indirection: bool,
+    lod_count: u32,
     default_pivot: &'a Pivot,
+    default_origin: Option<&'a Origin>,
+    atlases: &'a [Atlas],
     atlas_idx: usize,
+    layer: usize,
     diced: &'a DicedTexture,
-    uv_rects: &'a HashMap<u64, FRect>,
     vertices: Vec<Vertex>,
     uvs: Vec<Uv>,
     indices: Vec<usize>,
+    faces: Vec<usize>,
+    slices: Vec<u8>,
 }
 
 fn new_ctx<'a>(
-    atlas: &'a Atlas,
+    atlases: &'a [Atlas],
     atlas_idx: usize,
+    layer: usize,
     diced: &'a DicedTexture,
     prefs: &'a Prefs,
 ) -> Context<'a> {
     Context {
-        ppu: prefs.ppu,
+        unit_size: prefs.unit_size,
+        padded_unit_size: prefs.unit_size + prefs.padding * 2,
+        ppu: diced.ppu.unwrap_or(prefs.ppu),
         trim: prefs.trim_transparent,
+        y_up: prefs.y_up,
+        uv_bottom_left: prefs.uv_bottom_left,
+        clockwise: prefs.clockwise,
+        quads: prefs.build_quads,
+        weld: prefs.weld_vertices,
+        quantize: prefs.quantize,
+        instances: prefs.build_instances,
+        merge_uvs: prefs.merge_instance_uvs,
+        indirection: prefs.build_indirection,
+        lod_count: prefs.lod_count,
         default_pivot: &prefs.pivot,
+        default_origin: prefs.origin.as_ref(),
+        atlases,
         atlas_idx,
+        layer,
         diced,
-        uv_rects: &atlas.rects,
         vertices: vec![],
         uvs: vec![],
         indices: vec![],
+        faces: vec![],
+        slices: vec![],
     }
 }
 
-fn build_it(mut ctx: Context) -> DicedSprite {
-    for unit in ctx.diced.units.iter() {
-        let uv_rect = &ctx.uv_rects[&unit.hash];
-        build_unit(&mut ctx, &unit.rect, uv_rect);
+fn build_it(mut ctx: Context) -> Result<DicedSprite> {
+    for unit in ctx.diced.units.iter().filter(|u| u.lod == 0) {
+        let (atlas_idx, uv_rect) = find_uv_rect(&ctx, unit.hash)?;
+        build_unit(&mut ctx, &unit.rect, uv_rect, atlas_idx);
     }
+    if ctx.faces.iter().all(|idx| *idx == ctx.atlas_idx) {
+        ctx.faces.clear();
+    }
+    let grid = build_grid(&ctx);
+    let indirection = match ctx.indirection {
+        true => grid.as_ref().and_then(|g| encode_indirection(&ctx, g)),
+        false => None,
+    };
 
-    let pivot = ctx.diced.pivot.as_ref().unwrap_or(ctx.default_pivot);
-    let rect = eval_rect(&ctx, pivot);
-    offset_vertices(&mut ctx, &rect, pivot);
+    let mut lods = match ctx.quads && !ctx.diced.whole {
+        true => build_lods(&ctx)?,
+        false => vec![],
+    };
+    let (mut rect, offset, pivot) = match eval_origin(&ctx) {
+        Some(origin) => place_origin(&mut ctx, origin),
+        None => place_pivot(&mut ctx),
+    };
+    let (mut instances, mut instance_uvs) = match ctx.instances {
+        true => build_instances(&ctx),
+        false => (vec![], vec![]),
+    };
+    let mut outline = build_outline(&ctx, &offset);
+    for lod in lods.iter_mut() {
+        lod.vertices.iter_mut().for_each(|v| {
+            v.x -= offset.x;
+            v.y -= offset.y;
+        });
+    }
+    apply_conventions(
+        &mut ctx,
+        &mut rect,
+        &mut instances,
+        &mut instance_uvs,
+        &mut outline,
+        &mut lods,
+    );
+    if ctx.weld {
+        weld_vertices(&mut ctx);
+    }
+    if !ctx.quads {
+        ctx.vertices.clear();
+        ctx.uvs.clear();
+        ctx.indices.clear();
+        ctx.slices.clear();
+    }
+    let quantized = match ctx.quantize {
+        true => Some(quantize(&ctx, &offset)?),
+        false => None,
+    };
 
-    DicedSprite {
+    Ok(DicedSprite {
         id: ctx.diced.id.to_owned(),
         atlas_index: ctx.atlas_idx,
+        atlas_indices: ctx.faces,
+        layer: ctx.layer,
         vertices: ctx.vertices,
         uvs: ctx.uvs,
-        indices: ctx.indices,
+        indices: ctx.indices.try_into()?,
         rect,
-        pivot: pivot.to_owned(),
+        pivot,
+        group: ctx.diced.group.to_owned(),
+        whole: ctx.diced.whole,
+        indirection,
+        grid,
+        instances,
+        instance_uvs,
+        quantized,
+        outline,
+        border: ctx.diced.border.to_owned(),
+        slices: ctx.slices,
+        lods,
+    })
+}
+
+/// Builds lower-detail meshes from the units diced from downscaled source texture, clipped
+/// to the bounds of the full-detail mesh; expected to be invoked before offsetting to pivot.
+fn build_lods(ctx: &Context) -> Result<Vec<Lod>> {
+    let bounds = match ctx.trim {
+        true => eval_fit_rect(ctx),
+        false => eval_full_rect(ctx, &Pivot::new(0.0, 0.0)),
+    };
+    let mut lods = vec![];
+    for lod in 1..=ctx.lod_count {
+        let (mut vertices, mut uvs, mut indices, mut faces) = (vec![], vec![], vec![], vec![]);
+        for unit in ctx.diced.units.iter().filter(|u| u.lod == lod) {
+            let (atlas_idx, uv_rect) = find_uv_rect(ctx, unit.hash)?;
+            let rect = scale_unit_rect(ctx, &unit.rect);
+            let factor = (1 << lod) as f32;
+            let rect = FRect {
+                x: rect.x * factor,
+                y: rect.y * factor,
+                width: rect.width * factor,
+                height: rect.height * factor,
+            };
+            let Some((rect, uv_rect)) = clip_quad(&rect, uv_rect, &bounds) else {
+                continue;
+            };
+            push_quad((&mut vertices, &mut uvs, &mut indices), &rect, &uv_rect);
+            faces.extend([atlas_idx, atlas_idx]);
+        }
+        if faces.iter().all(|idx| *idx == ctx.atlas_idx) {
+            faces.clear();
+        }
+        lods.push(Lod {
+            atlas_indices: faces,
+            vertices,
+            uvs,
+            indices: indices.try_into()?,
+        });
+    }
+    Ok(lods)
+}
+
+/// Clips quad to specified bounds, adjusting the UVs proportionally; None when the quad
+/// is outside the bounds.
+fn clip_quad(rect: &FRect, uv_rect: &FRect, bounds: &Rect) -> Option<(FRect, FRect)> {
+    let x_min = rect.x.max(bounds.x);
+    let y_min = rect.y.max(bounds.y);
+    let x_max = (rect.x + rect.width).min(bounds.x + bounds.width);
+    let y_max = (rect.y + rect.height).min(bounds.y + bounds.height);
+    if x_min >= x_max || y_min >= y_max {
+        return None;
+    }
+    let u = |x: f32| uv_rect.x + (x - rect.x) / rect.width * uv_rect.width;
+    let v = |y: f32| uv_rect.y + (y - rect.y) / rect.height * uv_rect.height;
+    let rect = FRect {
+        x: x_min,
+        y: y_min,
+        width: x_max - x_min,
+        height: y_max - y_min,
+    };
+    let uv_rect = FRect {
+        x: u(x_min),
+        y: v(y_min),
+        width: u(x_max) - u(x_min),
+        height: v(y_max) - v(y_min),
+    };
+    Some((rect, uv_rect))
+}
+
+fn fits_border(border: &Border, size: &USize) -> bool {
+    border.left + border.right <= size.width && border.top + border.bottom <= size.height
+}
+
+/// Scales the outline traced over the source texture to local space and offsets it to
+/// the pivot, same as the vertices.
+fn build_outline(ctx: &Context, offset: &Vertex) -> Vec<Vec<Vertex>> {
+    let paths = ctx.diced.outline.iter().map(|path| {
+        let path = path
+            .iter()
+            .map(|v| Vertex::new(v.x / ctx.ppu - offset.x, v.y / ctx.ppu - offset.y));
+        path.collect()
+    });
+    paths.collect()
+}
+
+/// Converts the vertices to pixel space and the UVs to 16-bit normalized integers, as
+/// specified in [DicedSprite::quantized]; None when the vertices don't fit 16 bits.
+fn quantize(ctx: &Context, offset: &Vertex) -> Result<Quantized> {
+    let sign = if ctx.y_up { -1.0 } else { 1.0 };
+    let origin = Vertex::new(offset.x * ctx.ppu, sign * offset.y * ctx.ppu);
+    let mut vertices = Vec::with_capacity(ctx.vertices.len());
+    for vertex in ctx.vertices.iter() {
+        let x = (vertex.x * ctx.ppu + origin.x).round();
+        let y = (vertex.y * ctx.ppu + origin.y).round();
+        let range = i16::MIN as f32..=i16::MAX as f32;
+        if !range.contains(&x) || !range.contains(&y) {
+            return Err(Error::Quantize(ctx.diced.id.to_owned()));
+        }
+        vertices.push([x as i16, y as i16]);
+    }
+    let norm = |n: f32| (n.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+    let uvs = ctx.uvs.iter().map(|uv| [norm(uv.u), norm(uv.v)]).collect();
+    Ok(Quantized {
+        origin,
+        vertices,
+        uvs,
+    })
+}
+
+/// Maps the sprite units to the atlas cells, as specified in [DicedSprite::grid].
+fn build_grid(ctx: &Context) -> Option<Grid> {
+    if ctx.diced.whole || ctx.diced.scale > 1 || !ctx.faces.is_empty() {
+        return None;
+    }
+    let atlas = &ctx.atlases[ctx.atlas_idx];
+    let columns = ctx.diced.size.width.div_ceil(ctx.unit_size);
+    let rows = ctx.diced.size.height.div_ceil(ctx.unit_size);
+    let mut cells = vec![None; (columns * rows) as usize];
+    for unit in ctx.diced.units.iter().filter(|u| u.lod == 0) {
+        let idx = (unit.rect.y / ctx.unit_size) * columns + unit.rect.x / ctx.unit_size;
+        cells[idx as usize] = Some(atlas.cells[&unit.hash]);
+    }
+    Some(Grid {
+        columns,
+        rows,
+        cells,
+    })
+}
+
+/// Encodes the grid cells into texture, as specified in [DicedSprite::indirection].
+fn encode_indirection(ctx: &Context, grid: &Grid) -> Option<Texture> {
+    let atlas = &ctx.atlases[ctx.atlas_idx];
+    let columns = atlas.texture.width / ctx.padded_unit_size;
+    let mut pixels = Vec::with_capacity(grid.cells.len());
+    for cell in grid.cells.iter() {
+        let Some(cell) = cell else {
+            pixels.push(Pixel::default());
+            continue;
+        };
+        let (column, row) = (cell % columns, cell / columns);
+        if column > 0xFFF || row > 0xFFF {
+            return None;
+        }
+        let high = (column >> 8) | ((row >> 8) << 4);
+        pixels.push(Pixel::new(column as u8, row as u8, high as u8, 255));
     }
+    Some(Texture {
+        width: grid.columns,
+        height: grid.rows,
+        pixels,
+    })
 }
 
-fn build_unit(ctx: &mut Context, unit_rect: &URect, uv_rect: &FRect) {
-    let unit_rect = scale_unit_rect(ctx, unit_rect);
-    build_quad(ctx, &unit_rect, uv_rect);
+/// Converts the mesh quads into per-unit instances, optionally sharing identical UV rects.
+fn build_instances(ctx: &Context) -> (Vec<Instance>, Vec<Rect>) {
+    let mut instances = vec![];
+    let mut uvs = vec![];
+    let mut uv_indices = HashMap::new();
+    for idx in (0..ctx.vertices.len()).step_by(4) {
+        let (min, max) = (&ctx.vertices[idx], &ctx.vertices[idx + 2]);
+        let rect = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+        let (min, max) = (&ctx.uvs[idx], &ctx.uvs[idx + 2]);
+        let uv_rect = Rect::new(min.u, min.v, max.u - min.u, max.v - min.v);
+        let key = [uv_rect.x, uv_rect.y, uv_rect.width, uv_rect.height].map(f32::to_bits);
+        let uv = match ctx.merge_uvs {
+            true => *uv_indices.entry(key).or_insert(uvs.len()),
+            false => uvs.len(),
+        };
+        if uv == uvs.len() {
+            uvs.push(uv_rect);
+        }
+        instances.push(Instance { rect, uv });
+    }
+    (instances, uvs)
+}
+
+/// Converts the data built in the default conventions (Y-down vertices, top-left UV origin
+/// and counter-clockwise winding) to the conventions specified in the preferences.
+fn apply_conventions(
+    ctx: &mut Context,
+    rect: &mut Rect,
+    instances: &mut [Instance],
+    instance_uvs: &mut [Rect],
+    outline: &mut [Vec<Vertex>],
+    lods: &mut [Lod],
+) {
+    if ctx.y_up {
+        let lod_vertices = lods.iter_mut().flat_map(|l| l.vertices.iter_mut());
+        lod_vertices.for_each(|v| v.y = -v.y);
+        ctx.vertices.iter_mut().for_each(|v| v.y = -v.y);
+        outline.iter_mut().flatten().for_each(|v| v.y = -v.y);
+        rect.y = -(rect.y + rect.height);
+        instances
+            .iter_mut()
+            .for_each(|i| i.rect.y = -(i.rect.y + i.rect.height));
+    }
+    if ctx.uv_bottom_left {
+        ctx.uvs.iter_mut().for_each(|uv| uv.v = 1.0 - uv.v);
+        let lod_uvs = lods.iter_mut().flat_map(|l| l.uvs.iter_mut());
+        lod_uvs.for_each(|uv| uv.v = 1.0 - uv.v);
+        instance_uvs
+            .iter_mut()
+            .for_each(|r| r.y = 1.0 - (r.y + r.height));
+    }
+    if ctx.clockwise {
+        ctx.indices.chunks_mut(3).for_each(|face| face.swap(1, 2));
+        outline.iter_mut().for_each(|path| path.reverse());
+        for lod in lods.iter_mut() {
+            match &mut lod.indices {
+                Indices::U16(indices) => indices.chunks_mut(3).for_each(|f| f.swap(1, 2)),
+                Indices::U32(indices) => indices.chunks_mut(3).for_each(|f| f.swap(1, 2)),
+            }
+        }
+    }
+}
+
+/// Merges the vertices with identical positions and UVs, remapping the indices.
+fn weld_vertices(ctx: &mut Context) {
+    let mut welded = HashMap::new();
+    let mut vertices = vec![];
+    let mut uvs = vec![];
+    let mut slices = vec![];
+    let mut remap = Vec::with_capacity(ctx.vertices.len());
+    for (idx, (vertex, uv)) in ctx.vertices.iter().zip(ctx.uvs.iter()).enumerate() {
+        // Adding zero normalizes negative zeroes, so that their bits match positive ones.
+        let [x, y, u, v] = [vertex.x, vertex.y, uv.u, uv.v].map(|n| (n + 0.0).to_bits());
+        // Vertices of distinct nine-slice regions are stretched differently, hence not merged.
+        let slice = ctx.slices.get(idx).copied();
+        let idx = *welded.entry((x, y, u, v, slice)).or_insert_with(|| {
+            vertices.push(vertex.to_owned());
+            uvs.push(uv.to_owned());
+            slices.extend(slice);
+            vertices.len() - 1
+        });
+        remap.push(idx);
+    }
+    ctx.indices.iter_mut().for_each(|i| *i = remap[*i]);
+    ctx.vertices = vertices;
+    ctx.uvs = uvs;
+    ctx.slices = slices;
+}
+
+/// Resolves UV rect of the unit with specified hash; units are looked up in the sprite's
+/// atlas first, then in other atlases of the same group (eg, shipped atlases when patching).
+fn find_uv_rect<'a>(ctx: &Context<'a>, hash: u64) -> Result<(usize, &'a FRect)> {
+    let atlas = &ctx.atlases[ctx.atlas_idx];
+    if let Some(rect) = atlas.rects.get(&hash) {
+        return Ok((ctx.atlas_idx, rect));
+    }
+    let others = ctx.atlases.iter().enumerate();
+    others
+        .filter(|(_, other)| other.group == atlas.group)
+        .find_map(|(idx, other)| other.rects.get(&hash).map(|rect| (idx, rect)))
+        .ok_or(Error::Spec(
+            "Unit isn't packed into an atlas of the sprite group.",
+        ))
+}
+
+fn build_unit(ctx: &mut Context, unit_rect: &URect, uv_rect: &FRect, atlas_idx: usize) {
+    let Some(border) = ctx.diced.border.to_owned() else {
+        build_quad(ctx, &scale_unit_rect(ctx, unit_rect), uv_rect);
+        ctx.faces.extend([atlas_idx, atlas_idx]);
+        return;
+    };
+    let size = &ctx.diced.size;
+    let columns = slice_unit(
+        unit_rect.x,
+        unit_rect.width,
+        border.left,
+        size.width - border.right,
+    );
+    let rows = slice_unit(
+        unit_rect.y,
+        unit_rect.height,
+        border.top,
+        size.height - border.bottom,
+    );
+    for (y, height, row) in rows.iter() {
+        for (x, width, column) in columns.iter() {
+            let rect = URect::new(*x, *y, *width, *height);
+            let uv_rect = FRect {
+                x: uv_rect.x + (x - unit_rect.x) as f32 / unit_rect.width as f32 * uv_rect.width,
+                y: uv_rect.y + (y - unit_rect.y) as f32 / unit_rect.height as f32 * uv_rect.height,
+                width: *width as f32 / unit_rect.width as f32 * uv_rect.width,
+                height: *height as f32 / unit_rect.height as f32 * uv_rect.height,
+            };
+            build_quad(ctx, &scale_unit_rect(ctx, &rect), &uv_rect);
+            ctx.slices.extend([row * 3 + column; 4]);
+            ctx.faces.extend([atlas_idx, atlas_idx]);
+        }
+    }
+}
+
+/// Splits unit span at specified nine-slice lines; returns start, length and index of the
+/// slice (0 — before first line, 1 — between the lines, 2 — after second line) of each part.
+fn slice_unit(start: u32, length: u32, first: u32, second: u32) -> Vec<(u32, u32, u8)> {
+    let end = start + length;
+    let mut cuts = vec![start];
+    cuts.extend(
+        [first, second]
+            .into_iter()
+            .filter(|c| *c > start && *c < end),
+    );
+    cuts.dedup();
+    cuts.push(end);
+    let slice = |pos: u32| match pos {
+        p if p < first => 0,
+        p if p >= second => 2,
+        _ => 1,
+    };
+    cuts.windows(2)
+        .map(|w| (w[0], w[1] - w[0], slice(w[0])))
+        .collect()
 }
 
 fn scale_unit_rect(ctx: &Context, unit_rect: &URect) -> FRect {
@@ -88,7 +501,16 @@ fn scale_unit_rect(ctx: &Context, unit_rect: &URect) -> FRect {
 }
 
 fn build_quad(ctx: &mut Context, unit_rect: &FRect, uv_rect: &FRect) {
-    let i = ctx.vertices.len();
+    let mesh = (&mut ctx.vertices, &mut ctx.uvs, &mut ctx.indices);
+    push_quad(mesh, unit_rect, uv_rect);
+}
+
+fn push_quad(
+    (vertices, uvs, indices): (&mut Vec<Vertex>, &mut Vec<Uv>, &mut Vec<usize>),
+    unit_rect: &FRect,
+    uv_rect: &FRect,
+) {
+    let i = vertices.len();
 
     let x_min = unit_rect.x;
     let y_min = unit_rect.y;
@@ -100,21 +522,69 @@ fn build_quad(ctx: &mut Context, unit_rect: &FRect, uv_rect: &FRect) {
     let u_max = uv_rect.x + uv_rect.width;
     let v_max = uv_rect.y + uv_rect.height;
 
-    ctx.vertices.extend([
+    vertices.extend([
         Vertex { x: x_min, y: y_min },
         Vertex { x: x_min, y: y_max },
         Vertex { x: x_max, y: y_max },
         Vertex { x: x_max, y: y_min },
     ]);
 
-    ctx.uvs.extend([
+    uvs.extend([
         Uv { u: u_min, v: v_min },
         Uv { u: u_min, v: v_max },
         Uv { u: u_max, v: v_max },
         Uv { u: u_max, v: v_min },
     ]);
 
-    ctx.indices.extend([i, i + 1, i + 2, i + 2, i + 3, i]);
+    indices.extend([i, i + 1, i + 2, i + 2, i + 3, i]);
+}
+
+/// Resolves origin of the sprite in local space units, counted from top-left corner of the
+/// source texture; [None] when the sprite is positioned by a relative pivot.
+fn eval_origin(ctx: &Context) -> Option<Vertex> {
+    let origin = match (&ctx.diced.origin, &ctx.diced.pivot) {
+        (Some(origin), _) => origin,
+        (None, Some(_)) => return None,
+        (None, None) => ctx.default_origin?,
+    };
+    let (x, y) = origin.to_pixel(ctx.diced.size.width, ctx.diced.size.height);
+    Some(Vertex::new(x / ctx.ppu, y / ctx.ppu))
+}
+
+/// Offsets the vertices to the pivot relative to the sprite rect; returns the rect,
+/// applied offset and the pivot.
+fn place_pivot(ctx: &mut Context) -> (Rect, Vertex, Pivot) {
+    let pivot = ctx.diced.pivot.as_ref().unwrap_or(ctx.default_pivot);
+    let pivot = pivot.to_owned();
+    let top_pivot = match ctx.y_up {
+        true => Pivot::new(pivot.x, 1.0 - pivot.y),
+        false => pivot.to_owned(),
+    };
+    let rect = eval_rect(ctx, &top_pivot);
+    let offset = offset_vertices(ctx, &rect, &top_pivot);
+    (rect, offset, pivot)
+}
+
+/// Offsets the vertices to the origin fixed in the source texture space, so that trimming
+/// doesn't shift it; returns the rect, applied offset and the pivot relative to the rect.
+fn place_origin(ctx: &mut Context, origin: Vertex) -> (Rect, Vertex, Pivot) {
+    let mut rect = match ctx.trim {
+        true => eval_fit_rect(ctx),
+        false => eval_full_rect(ctx, &Pivot::new(0.0, 0.0)),
+    };
+    rect.x -= origin.x;
+    rect.y -= origin.y;
+    for vertex in ctx.vertices.iter_mut() {
+        vertex.x -= origin.x;
+        vertex.y -= origin.y;
+    }
+    let x = -rect.x / rect.width;
+    let y = -rect.y / rect.height;
+    let pivot = match ctx.y_up {
+        true => Pivot::new(x, 1.0 - y),
+        false => Pivot::new(x, y),
+    };
+    (rect, origin, pivot)
 }
 
 fn eval_rect(ctx: &Context, pivot: &Pivot) -> Rect {
@@ -151,7 +621,8 @@ fn eval_full_rect(ctx: &Context, pivot: &Pivot) -> Rect {
     Rect::new(x, y, width, height)
 }
 
-fn offset_vertices(ctx: &mut Context, rect: &Rect, pivot: &Pivot) {
+/// Offsets the vertices to the pivot; returns the applied offset.
+fn offset_vertices(ctx: &mut Context, rect: &Rect, pivot: &Pivot) -> Vertex {
     let mut offset_x = pivot.x * rect.width;
     let mut offset_y = pivot.y * rect.height;
 
@@ -164,12 +635,14 @@ fn offset_vertices(ctx: &mut Context, rect: &Rect, pivot: &Pivot) {
         ctx.vertices[idx].x -= offset_x;
         ctx.vertices[idx].y -= offset_y;
     }
+    Vertex::new(offset_x, offset_y)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::fixtures::*;
     use crate::models::*;
+    use std::collections::HashSet;
 
     #[test]
     fn can_build_with_defaults() {
@@ -212,6 +685,23 @@ mod tests {
         assert_eq!(quad.bottom_right, Vertex::new(0.5, 0.5));
     }
 
+    #[test]
+    fn sprite_ppu_overrides_default() {
+        let mut sprites = build(vec![&(&B1X1, 2.0), &R1X1], &defaults());
+        sprites.sort_by(|a, b| a.rect.width.total_cmp(&b.rect.width));
+        let quad = Quad::from_1x1(&sprites[0]);
+        assert_eq!(quad.bottom_right, Vertex::new(0.5, 0.5));
+        assert_eq!(sprites[0].rect, Rect::new(0.0, 0.0, 0.5, 0.5));
+        let quad = Quad::from_1x1(&sprites[1]);
+        assert_eq!(quad.bottom_right, Vertex::new(1.0, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sprite PPU can't be zero or negative.")]
+    fn errs_when_sprite_ppu_zero() {
+        build(vec![&(&B1X1, 0.0)], &defaults());
+    }
+
     #[test]
     fn zero_pivot_doesnt_offset_vertices() {
         let prefs = Prefs {
@@ -357,6 +847,104 @@ mod tests {
         assert_eq!(sprites[0].pivot, Pivot::new(0.5, 0.5));
     }
 
+    #[test]
+    fn anchored_origin_doesnt_shift_when_trimmed() {
+        let origin = Origin::Anchor(Anchor::BottomCenter);
+        for trim_transparent in [true, false] {
+            let prefs = Prefs {
+                trim_transparent,
+                ..defaults()
+            };
+            let sprite = &build(vec![&(&MTTT, origin.to_owned())], &prefs)[0];
+            let quad = Quad::from_1x1(sprite);
+            assert_eq!(quad.top_left, Vertex::new(-1.0, -2.0));
+            assert_eq!(quad.bottom_right, Vertex::new(0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn pivot_of_origin_is_relative_to_rect() {
+        let origin = Origin::Anchor(Anchor::BottomCenter);
+        let prefs = Prefs {
+            trim_transparent: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&(&MTTT, origin.to_owned())], &prefs)[0];
+        assert_eq!(sprite.rect, Rect::new(-1.0, -2.0, 1.0, 1.0));
+        assert_eq!(sprite.pivot, Pivot::new(1.0, 2.0));
+        let prefs = Prefs {
+            trim_transparent: false,
+            ..defaults()
+        };
+        let sprite = &build(vec![&(&MTTT, origin)], &prefs)[0];
+        assert_eq!(sprite.rect, Rect::new(-1.0, -2.0, 2.0, 2.0));
+        assert_eq!(sprite.pivot, Pivot::new(0.5, 1.0));
+    }
+
+    #[test]
+    fn pixel_origin_is_relative_to_source_texture() {
+        let prefs = Prefs {
+            trim_transparent: true,
+            ppu: 2.0,
+            ..defaults()
+        };
+        let sprite = &build(vec![&(&TTTM, Origin::Pixel(2.0, 1.0))], &prefs)[0];
+        let quad = Quad::from_1x1(sprite);
+        assert_eq!(quad.top_left, Vertex::new(-0.5, 0.0));
+        assert_eq!(quad.bottom_right, Vertex::new(0.0, 0.5));
+    }
+
+    #[test]
+    fn default_origin_doesnt_override_per_sprite_pivot() {
+        let prefs = Prefs {
+            origin: Some(Origin::Anchor(Anchor::BottomRight)),
+            ..defaults()
+        };
+        let mut sprites = build(vec![&R1X1, &(&B1X1, (0.5, 0.5))], &prefs);
+        sprites.sort_by(|a, b| a.rect.x.total_cmp(&b.rect.x));
+        assert_eq!(
+            Quad::from_1x1(&sprites[0]).top_left,
+            Vertex::new(-1.0, -1.0)
+        );
+        assert_eq!(
+            Quad::from_1x1(&sprites[1]).top_left,
+            Vertex::new(-0.5, -0.5)
+        );
+    }
+
+    #[test]
+    fn when_y_up_pivot_of_origin_is_relative_to_bottom_left() {
+        let prefs = Prefs {
+            y_up: true,
+            origin: Some(Origin::Anchor(Anchor::BottomLeft)),
+            ..defaults()
+        };
+        let sprite = &build(vec![&B1X1], &prefs)[0];
+        let quad = Quad::from_1x1(sprite);
+        assert_eq!(quad.top_left, Vertex::new(0.0, 1.0));
+        assert_eq!(quad.bottom_right, Vertex::new(1.0, 0.0));
+        assert_eq!(sprite.rect, Rect::new(0.0, 0.0, 1.0, 1.0));
+        assert_eq!(sprite.pivot, Pivot::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn group_is_assigned_from_source() {
+        let sprites = &build(vec![&R1X1, &(&B1X1, "a")], &defaults());
+        assert!(sprites.iter().any(|s| s.group.is_none()));
+        assert!(sprites.iter().any(|s| s.group.as_deref() == Some("a")));
+    }
+
+    #[test]
+    fn layer_is_counted_per_group() {
+        let prefs = Prefs {
+            atlas_size_limit: 1,
+            ..defaults()
+        };
+        let sprites = &build(vec![&(&R1X1, "a"), &(&G1X1, "a"), &(&B1X1, "b")], &prefs);
+        let layers = sprites.iter().map(|s| (s.atlas_index, s.layer));
+        assert_eq!(layers.collect::<Vec<_>>(), vec![(0, 0), (1, 1), (2, 0)]);
+    }
+
     #[test]
     fn sprite_rect_size_equals_source_texture_divided_by_ppu() {
         let prefs = Prefs {
@@ -482,6 +1070,574 @@ mod tests {
         assert!(&build(vec![&TTTT], &prefs).is_empty());
     }
 
+    #[test]
+    fn faces_sample_atlas_index_by_default() {
+        assert!(build(vec![&RGBY], &defaults())[0].atlas_indices.is_empty());
+    }
+
+    #[test]
+    fn faces_sample_atlases_containing_units() {
+        let prefs = defaults();
+        let shipped = crate::dicer::dice(&[R1X1.sprite()], &prefs).unwrap();
+        let shipped = crate::packer::pack(shipped, &prefs).unwrap();
+        let layouts = shipped.iter().map(|a| crate::packer::layout(a, &prefs));
+        let layouts = layouts.collect::<Vec<_>>();
+        let diced = crate::dicer::dice(&[RGBY.sprite()], &prefs).unwrap();
+        let packed = crate::packer::patch(diced, &layouts, &prefs).unwrap();
+        let sprite = &crate::builder::build(&packed, &prefs).unwrap()[0];
+        assert_eq!(sprite.atlas_index, 1);
+        assert_eq!(sprite.atlas_indices, vec![0, 0, 1, 1, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unit isn't packed into an atlas of the sprite group.")]
+    fn errs_when_unit_is_not_packed_into_atlas_of_group() {
+        let prefs = defaults();
+        let diced = crate::dicer::dice(&[RGBY.sprite()], &prefs).unwrap();
+        let mut packed = crate::packer::pack(diced, &prefs).unwrap();
+        packed[0].rects.clear();
+        crate::builder::build(&packed, &prefs).unwrap();
+    }
+
+    #[test]
+    fn whole_sprite_is_single_quad() {
+        let prefs = Prefs {
+            reuse_threshold: 0.5,
+            build_indirection: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&BTGT], &prefs)[0];
+        assert!(sprite.whole);
+        assert_eq!(sprite.vertices.len(), 4);
+        assert_eq!(sprite.vertices[2], Vertex::new(1.0, 2.0));
+        assert!(sprite.indirection.is_none());
+        assert!(sprite.grid.is_none());
+    }
+
+    #[test]
+    fn grid_maps_units_to_atlas_cells() {
+        let sprites = [BTGT.sprite()];
+        let diced = crate::dicer::dice(&sprites, &defaults()).unwrap();
+        let packed = crate::packer::pack(diced, &defaults()).unwrap();
+        let sprite = &crate::builder::build(&packed, &defaults()).unwrap()[0];
+        let grid = sprite.grid.as_ref().unwrap();
+        let atlas = &packed[0].texture;
+        assert_eq!((grid.columns, grid.rows), (2, 2));
+        assert_eq!((grid.cells[1], grid.cells[3]), (None, None));
+        assert_eq!(atlas.pixels[grid.cells[0].unwrap() as usize], B);
+        assert_eq!(atlas.pixels[grid.cells[2].unwrap() as usize], G);
+    }
+
+    #[test]
+    fn grid_size_is_rounded_up_to_units() {
+        let prefs = Prefs {
+            unit_size: 2,
+            ..defaults()
+        };
+        let sprite = &build(vec![&RGB1X3], &prefs)[0];
+        let grid = sprite.grid.as_ref().unwrap();
+        assert_eq!((grid.columns, grid.rows), (1, 2));
+        assert!(grid.cells.iter().all(|c| c.is_some()));
+    }
+
+    #[test]
+    fn indirection_is_not_built_by_default() {
+        assert!(build(vec![&RGB4X4], &defaults())[0].indirection.is_none());
+    }
+
+    #[test]
+    fn indirection_maps_units_to_atlas_cells() {
+        let prefs = Prefs {
+            build_indirection: true,
+            ..defaults()
+        };
+        for source in [&RGB4X4, &BTGT] {
+            let sprites = [source.sprite()];
+            let diced = crate::dicer::dice(&sprites, &prefs).unwrap();
+            let packed = crate::packer::pack(diced, &prefs).unwrap();
+            let sprite = &crate::builder::build(&packed, &prefs).unwrap()[0];
+            let atlas = &packed[0].texture;
+            let page = sprite.indirection.as_ref().unwrap();
+            assert_eq!((page.width, page.height), (source.width, source.height));
+            for (texel, pixel) in page.pixels.iter().zip(source.pixels.iter()) {
+                if pixel.a() == 0 {
+                    assert_eq!(texel.a(), 0);
+                    continue;
+                }
+                let column = texel.r() as u32 | (texel.b() as u32 & 0xF) << 8;
+                let row = texel.g() as u32 | (texel.b() as u32 >> 4) << 8;
+                assert_eq!(texel.a(), 255);
+                assert_eq!(atlas.pixels[(row * atlas.width + column) as usize], *pixel);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Either quads or instances should be built.")]
+    fn errs_when_neither_quads_nor_instances_built() {
+        let prefs = Prefs {
+            build_quads: false,
+            ..defaults()
+        };
+        build(vec![&R1X1], &prefs);
+    }
+
+    #[test]
+    fn instances_are_not_built_by_default() {
+        let sprite = &build(vec![&RGB4X4], &defaults())[0];
+        assert!(sprite.instances.is_empty());
+        assert!(sprite.instance_uvs.is_empty());
+    }
+
+    #[test]
+    fn instances_mirror_quads() {
+        let prefs = Prefs {
+            build_instances: true,
+            pivot: Pivot { x: 0.5, y: 0.5 },
+            ..defaults()
+        };
+        let sprite = &build(vec![&BTGT], &prefs)[0];
+        assert_eq!(sprite.instances.len(), sprite.vertices.len() / 4);
+        for (idx, instance) in sprite.instances.iter().enumerate() {
+            let (min, max) = (&sprite.vertices[idx * 4], &sprite.vertices[idx * 4 + 2]);
+            assert_eq!(instance.rect.x, min.x);
+            assert_eq!(instance.rect.y, min.y);
+            assert_eq!(instance.rect.width, max.x - min.x);
+            assert_eq!(instance.rect.height, max.y - min.y);
+            let uv = &sprite.instance_uvs[instance.uv];
+            assert_eq!((uv.x, uv.y), (sprite.uvs[idx * 4].u, sprite.uvs[idx * 4].v));
+        }
+    }
+
+    #[test]
+    fn when_quads_disabled_only_instances_are_built() {
+        let prefs = Prefs {
+            build_quads: false,
+            build_instances: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&BTGT], &prefs)[0];
+        assert!(sprite.vertices.is_empty() && sprite.uvs.is_empty() && sprite.indices.is_empty());
+        assert_eq!(sprite.instances.len(), 2);
+        assert_eq!(sprite.rect, Rect::new(0.0, 0.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn when_merging_identical_uvs_are_shared() {
+        let prefs = Prefs {
+            build_instances: true,
+            merge_instance_uvs: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&RGB4X4], &prefs)[0];
+        assert_eq!(sprite.instances.len(), 16);
+        assert_eq!(sprite.instance_uvs.len(), 3);
+    }
+
+    #[test]
+    fn when_not_merging_each_instance_has_uv() {
+        let prefs = Prefs {
+            build_instances: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&RGB4X4], &prefs)[0];
+        assert_eq!(sprite.instance_uvs.len(), 16);
+        assert!(sprite.instances.iter().enumerate().all(|(i, s)| s.uv == i));
+    }
+
+    #[test]
+    fn when_y_up_vertices_and_rect_are_flipped() {
+        let prefs = Prefs {
+            y_up: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&B1X1], &prefs)[0];
+        let quad = Quad::from_1x1(sprite);
+        assert_eq!(quad.top_left, Vertex::new(0.0, 1.0));
+        assert_eq!(quad.bottom_right, Vertex::new(1.0, 0.0));
+        assert_eq!(sprite.rect, Rect::new(0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn when_y_up_pivot_is_relative_to_bottom_left() {
+        let prefs = Prefs {
+            y_up: true,
+            pivot: Pivot { x: 0.0, y: 1.0 },
+            ..defaults()
+        };
+        let sprite = &build(vec![&B1X1], &prefs)[0];
+        let quad = Quad::from_1x1(sprite);
+        assert_eq!(quad.top_left, Vertex::new(0.0, 0.0));
+        assert_eq!(quad.bottom_right, Vertex::new(1.0, -1.0));
+        assert_eq!(sprite.rect, Rect::new(0.0, -1.0, 1.0, 1.0));
+        assert_eq!(sprite.pivot, Pivot::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn when_uv_bottom_left_uvs_are_flipped() {
+        let prefs = Prefs {
+            uv_bottom_left: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&B1X1], &prefs)[0];
+        assert_eq!(sprite.uvs[0], Uv::new(0.0, 1.0));
+        assert_eq!(sprite.uvs[2], Uv::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn when_clockwise_faces_are_reversed() {
+        let prefs = Prefs {
+            clockwise: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&B1X1], &prefs)[0];
+        assert_eq!(sprite.indices, Indices::U16(vec![0, 2, 1, 2, 0, 3]));
+    }
+
+    #[test]
+    fn conventions_are_applied_to_instances() {
+        let prefs = Prefs {
+            y_up: true,
+            uv_bottom_left: true,
+            build_instances: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&BTGT], &prefs)[0];
+        for (idx, instance) in sprite.instances.iter().enumerate() {
+            let vertices = &sprite.vertices[idx * 4..idx * 4 + 4];
+            let min_y = vertices.iter().map(|v| v.y).fold(f32::INFINITY, f32::min);
+            let max_y = vertices
+                .iter()
+                .map(|v| v.y)
+                .fold(f32::NEG_INFINITY, f32::max);
+            assert_eq!(
+                (instance.rect.y, instance.rect.height),
+                (min_y, max_y - min_y)
+            );
+            let uvs = &sprite.uvs[idx * 4..idx * 4 + 4];
+            let min_v = uvs.iter().map(|uv| uv.v).fold(f32::INFINITY, f32::min);
+            assert_eq!(sprite.instance_uvs[instance.uv].y, min_v);
+        }
+    }
+
+    #[test]
+    fn welded_vertices_are_unique() {
+        let prefs = Prefs {
+            weld_vertices: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&RGB4X4], &prefs)[0];
+        let pairs = sprite.vertices.iter().zip(sprite.uvs.iter());
+        let keys = pairs.map(|(v, uv)| format!("{v:?}{uv:?}"));
+        assert_eq!(keys.collect::<HashSet<_>>().len(), sprite.vertices.len());
+        assert!(sprite.vertices.len() < 16 * 4);
+    }
+
+    #[test]
+    fn welded_faces_are_same_as_not_welded() {
+        let welded = Prefs {
+            weld_vertices: true,
+            ..defaults()
+        };
+        let welded = &build(vec![&RGB4X4], &welded)[0];
+        let sprite = &build(vec![&RGB4X4], &defaults())[0];
+        let corners = |s: &DicedSprite| -> Vec<_> {
+            let idx = s.indices.iter();
+            idx.map(|i| (s.vertices[i].to_owned(), s.uvs[i].to_owned()))
+                .collect()
+        };
+        assert_eq!(corners(welded), corners(sprite));
+    }
+
+    #[test]
+    fn slices_are_empty_without_border() {
+        let sprite = &build(vec![&RGB4X4], &defaults())[0];
+        assert!(sprite.border.is_none());
+        assert!(sprite.slices.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Sprite border exceeds texture dimensions.")]
+    fn errs_when_border_exceeds_texture() {
+        let border = Border {
+            left: 3,
+            right: 2,
+            ..Border::default()
+        };
+        build(vec![&(&RGB4X4, border)], &defaults());
+    }
+
+    #[test]
+    fn quads_are_split_at_slice_lines() {
+        let prefs = Prefs {
+            unit_size: 4,
+            ..defaults()
+        };
+        let border = Border {
+            left: 1,
+            top: 1,
+            right: 1,
+            bottom: 1,
+        };
+        let sprite = &build(vec![&(&RGB4X4, border.to_owned())], &prefs)[0];
+        let whole = &build(vec![&RGB4X4], &prefs)[0];
+        assert_eq!(sprite.border, Some(border));
+        assert_eq!(sprite.vertices.len(), 9 * 4);
+        assert_eq!(sprite.indices.len(), 9 * 6);
+        let slices = (0..9).flat_map(|s| [s; 4]).collect::<Vec<_>>();
+        assert_eq!(sprite.slices, slices);
+        assert_eq!(sprite.vertices[16], Vertex::new(1.0, 1.0));
+        assert_eq!(sprite.vertices[18], Vertex::new(3.0, 3.0));
+        let (min, max) = (&whole.uvs[0], &whole.uvs[2]);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        assert_eq!(sprite.uvs[16].u, lerp(min.u, max.u, 0.25));
+        assert_eq!(sprite.uvs[18].v, lerp(min.v, max.v, 0.75));
+    }
+
+    #[test]
+    fn quads_on_slice_lines_are_not_split() {
+        let prefs = Prefs {
+            unit_size: 2,
+            ..defaults()
+        };
+        let border = Border {
+            left: 2,
+            top: 2,
+            right: 2,
+            bottom: 2,
+        };
+        let sprite = &build(vec![&(&RGB4X4, border)], &prefs)[0];
+        assert_eq!(sprite.vertices.len(), 4 * 4);
+        let slices = sprite.slices.iter().collect::<HashSet<_>>();
+        assert_eq!(slices, HashSet::from([&0, &2, &6, &8]));
+    }
+
+    #[test]
+    fn welded_faces_dont_share_slices() {
+        let prefs = Prefs {
+            weld_vertices: true,
+            ..defaults()
+        };
+        let border = Border {
+            left: 1,
+            top: 2,
+            right: 1,
+            bottom: 1,
+        };
+        let sprite = &build(vec![&(&RGB4X4, border)], &prefs)[0];
+        assert_eq!(sprite.slices.len(), sprite.vertices.len());
+        let indices = sprite.indices.iter().collect::<Vec<_>>();
+        for face in indices.chunks(3) {
+            assert!(
+                face.iter()
+                    .all(|i| sprite.slices[*i] == sprite.slices[face[0]])
+            );
+        }
+    }
+
+    #[test]
+    fn lods_are_not_built_by_default() {
+        assert!(build(vec![&RGB4X4], &defaults())[0].lods.is_empty());
+    }
+
+    #[test]
+    fn lods_have_fewer_quads() {
+        let prefs = Prefs {
+            lod_count: 2,
+            ..defaults()
+        };
+        let sprite = &build(vec![&RGB4X4], &prefs)[0];
+        assert_eq!(sprite.lods.len(), 2);
+        assert_eq!(sprite.lods[0].vertices.len(), 4 * 4);
+        assert_eq!(sprite.lods[1].vertices.len(), 4);
+        assert_eq!(sprite.lods[1].indices.len(), 6);
+        assert_eq!(sprite.lods[1].vertices[2], Vertex::new(4.0, 4.0));
+    }
+
+    #[test]
+    fn lods_are_not_built_for_whole_sprites() {
+        let prefs = Prefs {
+            lod_count: 2,
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let sprite = &build(vec![&PLT4X4], &prefs)[0];
+        assert!(sprite.whole);
+        assert!(sprite.lods.is_empty());
+    }
+
+    #[test]
+    fn lods_stay_within_sprite_rect() {
+        let prefs = Prefs {
+            lod_count: 2,
+            pivot: Pivot::new(0.5, 0.5),
+            ..defaults()
+        };
+        let sprite = &build(vec![&RGB3X1], &prefs)[0];
+        let rect = &sprite.rect;
+        for vertex in sprite.lods.iter().flat_map(|l| l.vertices.iter()) {
+            assert!(vertex.x >= rect.x && vertex.x <= rect.x + rect.width);
+            assert!(vertex.y >= rect.y && vertex.y <= rect.y + rect.height);
+        }
+        assert_eq!(sprite.lods[0].vertices[6], Vertex::new(1.5, 0.5));
+    }
+
+    #[test]
+    fn clipped_lod_uvs_are_interpolated() {
+        let prefs = Prefs {
+            lod_count: 1,
+            ..defaults()
+        };
+        let lod = &build(vec![&RGB3X1], &prefs)[0].lods[0];
+        assert_eq!(lod.vertices[6], Vertex::new(3.0, 1.0));
+        let clipped = lod.uvs[6].u - lod.uvs[4].u;
+        let full = lod.uvs[2].u - lod.uvs[0].u;
+        assert_eq!(clipped, full / 2.0);
+    }
+
+    #[test]
+    fn sprites_over_vertex_budget_are_diced_with_larger_units() {
+        let prefs = Prefs {
+            vertex_budget: 16,
+            ..defaults()
+        };
+        let sprite = &crate::dice(&[RGB4X4.sprite()], &prefs).unwrap().sprites[0];
+        assert!(!sprite.whole);
+        assert!(sprite.grid.is_none());
+        assert_eq!(sprite.vertices.len(), 16);
+    }
+
+    #[test]
+    fn sprites_over_vertex_budget_are_single_quad_as_last_resort() {
+        let prefs = Prefs {
+            vertex_budget: 4,
+            ..defaults()
+        };
+        let sprite = &crate::dice(&[RGB4X4.sprite()], &prefs).unwrap().sprites[0];
+        assert!(sprite.whole);
+        assert_eq!(sprite.vertices.len(), 4);
+    }
+
+    #[test]
+    fn vertex_budget_accounts_welded_vertices() {
+        let prefs = Prefs {
+            weld_vertices: true,
+            ..defaults()
+        };
+        let welded = build(vec![&RGB4X4], &prefs)[0].vertices.len();
+        assert!(welded < 16 * 4);
+        let prefs = Prefs {
+            vertex_budget: welded as u32,
+            ..prefs
+        };
+        let sprite = &crate::dice(&[RGB4X4.sprite()], &prefs).unwrap().sprites[0];
+        assert_eq!(sprite.vertices.len(), welded);
+    }
+
+    #[test]
+    fn errs_naming_sprite_when_vertex_budget_cant_be_met() {
+        let prefs = Prefs {
+            vertex_budget: 8,
+            ..defaults()
+        };
+        let border = Border {
+            left: 1,
+            top: 1,
+            right: 1,
+            bottom: 1,
+        };
+        let sprites = [(&RGB4X4, border).sprite()];
+        let result = crate::dice(&sprites, &prefs);
+        assert!(result.is_err_and(|e| e.to_string() == "Sprite 'TEST' exceeds the vertex budget."));
+    }
+
+    #[test]
+    fn quantized_is_not_built_by_default() {
+        assert!(build(vec![&PLT3X3], &defaults())[0].quantized.is_none());
+    }
+
+    #[test]
+    fn quantized_vertices_are_source_pixels() {
+        for y_up in [false, true] {
+            let prefs = Prefs {
+                ppu: 2.0,
+                pivot: Pivot::new(0.5, 0.5),
+                quantize: true,
+                y_up,
+                ..defaults()
+            };
+            let sprite = &build(vec![&PLT3X3], &prefs)[0];
+            let quantized = sprite.quantized.as_ref().unwrap();
+            assert_eq!(
+                quantized.origin,
+                Vertex::new(1.5, if y_up { -1.5 } else { 1.5 })
+            );
+            for (q, vertex) in quantized.vertices.iter().zip(sprite.vertices.iter()) {
+                assert!((0..=3).contains(&q[0].abs()) && (0..=3).contains(&q[1].abs()));
+                assert_eq!((q[0] as f32 - quantized.origin.x) / 2.0, vertex.x);
+                assert_eq!((q[1] as f32 - quantized.origin.y) / 2.0, vertex.y);
+            }
+        }
+    }
+
+    #[test]
+    fn quantized_uvs_are_normalized() {
+        let prefs = Prefs {
+            quantize: true,
+            ..defaults()
+        };
+        let sprite = &build(vec![&PLT3X3], &prefs)[0];
+        let quantized = sprite.quantized.as_ref().unwrap();
+        for (q, uv) in quantized.uvs.iter().zip(sprite.uvs.iter()) {
+            assert!((q[0] as f32 / 65535.0 - uv.u).abs() < 1.0 / 65535.0);
+            assert!((q[1] as f32 / 65535.0 - uv.v).abs() < 1.0 / 65535.0);
+        }
+    }
+
+    #[test]
+    fn quantized_uvs_recover_atlas_pixels() {
+        let prefs = Prefs {
+            padding: 1,
+            quantize: true,
+            ..defaults()
+        };
+        let arts = crate::dice(&[PLT4X4.sprite()], &prefs).unwrap();
+        let atlas = &arts.atlases[0];
+        assert_ne!(65535 % atlas.width, 0);
+        let sprite = &arts.sprites[0];
+        let quantized = sprite.quantized.as_ref().unwrap();
+        for (q, uv) in quantized.uvs.iter().zip(sprite.uvs.iter()) {
+            let u = (q[0] as f64 / 65535.0 * atlas.width as f64).round();
+            let v = (q[1] as f64 / 65535.0 * atlas.height as f64).round();
+            assert!((u - uv.u as f64 * atlas.width as f64).abs() < 1e-3);
+            assert!((v - uv.v as f64 * atlas.height as f64).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn errs_when_quantized_vertices_exceed_range() {
+        let prefs = Prefs {
+            quantize: true,
+            ..defaults()
+        };
+        let mut texture = Texture {
+            width: 40000,
+            height: 1,
+            pixels: vec![T; 40000],
+        };
+        texture.pixels[39999] = R;
+        let sprites = [SourceSprite {
+            texture,
+            ..R1X1.sprite()
+        }];
+        let diced = crate::dicer::dice(&sprites, &prefs).unwrap();
+        let packed = crate::packer::pack(diced, &prefs).unwrap();
+        assert!(
+            crate::builder::build(&packed, &prefs)
+                .is_err_and(|e| e.to_string() == "Sprite 'TEST' exceeds the quantization range.")
+        );
+    }
+
     #[test]
     fn reports_progress() {
         let progress = sample_progress(|p| drop(build(vec![&BTGT], &p)));
@@ -1,71 +1,250 @@
 use crate::models::*;
+use crate::outline;
+use std::borrow::Borrow;
 use std::cmp;
-use std::collections::HashSet;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Chops source sprite textures and collects unique units.
 pub(crate) fn dice(sprites: &[SourceSprite], prefs: &Prefs) -> Result<Vec<DicedTexture>> {
+    dice_stream(sprites.iter(), prefs)
+}
+
+/// Chops source sprite textures supplied by specified iterator one at a time; the source
+/// textures are not retained, only the pixels of the unique units are kept.
+pub(crate) fn dice_stream<S: Borrow<SourceSprite>>(
+    mut sprites: impl Iterator<Item = S>,
+    prefs: &Prefs,
+) -> Result<Vec<DicedTexture>> {
+    validate(prefs)?;
+
+    let mut textures = vec![];
+    let mut pool = HashMap::new();
+    let mut originals = HashMap::new();
+    let mut idx = 0;
+    while let Some(sprite) = sprites.next() {
+        // Total is not known in advance when streaming, so estimating it from the remaining.
+        let (min, max) = sprites.size_hint();
+        let len = idx + 1 + max.unwrap_or(min);
+        Progress::report(prefs, 1, idx, len, "Dicing source textures");
+        idx += 1;
+        let sprite = sprite.borrow();
+        let hash = prefs.alias_duplicates.then(|| hash_source(sprite));
+        if let Some(hash) = hash
+            && alias_duplicate(sprite, hash, &mut textures, &originals, |texture, _| {
+                same_pixels(texture, &sprite.texture, prefs)
+            })
+        {
+            continue;
+        }
+        let mut ctx = new_ctx(sprite, &mut pool, prefs);
+        if let Some(texture) = dice_it(&mut ctx) {
+            if let Some(hash) = hash {
+                originals
+                    .entry(hash)
+                    .or_insert_with(Vec::new)
+                    .push(textures.len());
+            }
+            textures.push(texture);
+        }
+    }
+
+    if prefs.reuse_threshold > 0.0 {
+        pack_low_reuse_whole(&mut textures, prefs);
+    }
+
+    Ok(textures)
+}
+
+fn validate(prefs: &Prefs) -> Result<()> {
     if prefs.unit_size == 0 {
         return Err(Error::Spec("Unit size can't be zero."));
     }
     if prefs.padding > prefs.unit_size {
         return Err(Error::Spec("Padding can't be above unit size."));
     }
+    if prefs.lod_count > 16 {
+        return Err(Error::Spec("LOD count can't be above 16."));
+    }
+    if prefs.vertex_budget > 0 && !prefs.build_quads {
+        return Err(Error::Spec("Vertex budget requires building quads."));
+    }
+    Ok(())
+}
+
+/// Chops opaque area of each source sprite texture as a single unit, without dicing.
+pub(crate) fn dice_whole(sprites: &[SourceSprite], prefs: &Prefs) -> Result<Vec<DicedTexture>> {
+    validate(prefs)?;
 
     let mut textures = vec![];
+    let mut originals = HashMap::new();
+    // Index of the source sprite each texture is chopped from.
+    let mut sources: Vec<usize> = vec![];
     for (idx, sprite) in sprites.iter().enumerate() {
-        Progress::report(prefs, 1, idx, sprites.len(), "Dicing source textures");
-        let ctx = new_ctx(sprite, prefs);
-        if let Some(texture) = dice_it(&ctx) {
-            textures.push(texture);
+        Progress::report(prefs, 1, idx, sprites.len(), "Trimming source textures");
+        if sprite.texture.pixels.iter().all(|p| p.a() == 0) {
+            continue;
         }
+        let hash = prefs.alias_duplicates.then(|| hash_source(sprite));
+        if let Some(hash) = hash {
+            let same = |_: &DicedTexture, idx: usize| {
+                let source = &sprites[sources[idx]].texture;
+                source.width == sprite.texture.width && source.pixels == sprite.texture.pixels
+            };
+            if alias_duplicate(sprite, hash, &mut textures, &originals, same) {
+                continue;
+            }
+            originals
+                .entry(hash)
+                .or_insert_with(Vec::new)
+                .push(textures.len());
+        }
+        let unit = chop_whole(&sprite.texture, prefs.padding);
+        sources.push(idx);
+        let outline = match prefs.build_outline {
+            true => outline::trace(
+                &sprite.texture,
+                prefs.outline_threshold,
+                prefs.outline_tolerance,
+            ),
+            false => vec![],
+        };
+        textures.push(DicedTexture {
+            id: sprite.id.to_owned(),
+            size: USize::new(sprite.texture.width, sprite.texture.height),
+            unique: HashSet::from([unit.hash]),
+            pivot: sprite.pivot.to_owned(),
+            origin: sprite.origin.to_owned(),
+            group: sprite.group.to_owned(),
+            ppu: sprite.ppu,
+            outline,
+            border: sprite.border.to_owned(),
+            units: vec![unit],
+            scale: 1,
+            whole: true,
+            aliases: vec![],
+        });
     }
-
     Ok(textures)
 }
 
+/// When a previously diced texture has pixels and settings identical to specified sprite,
+/// registers the sprite as alias of the texture and returns true. The hashes only narrow
+/// down the candidates: the pixels are compared with the specified function to guard against
+/// hash collisions.
+fn alias_duplicate(
+    sprite: &SourceSprite,
+    hash: u64,
+    textures: &mut [DicedTexture],
+    originals: &HashMap<u64, Vec<usize>>,
+    same_pixels: impl Fn(&DicedTexture, usize) -> bool,
+) -> bool {
+    let mut candidates = originals.get(&hash).into_iter().flatten();
+    let Some(idx) = candidates.find(|idx| {
+        let texture = &textures[**idx];
+        is_duplicate(texture, sprite) && same_pixels(texture, **idx)
+    }) else {
+        return false;
+    };
+    textures[*idx].aliases.push(sprite.id.to_owned());
+    true
+}
+
+fn is_duplicate(texture: &DicedTexture, sprite: &SourceSprite) -> bool {
+    texture.pivot == sprite.pivot
+        && texture.origin == sprite.origin
+        && texture.group == sprite.group
+        && texture.ppu == sprite.ppu
+        && texture.border == sprite.border
+}
+
 struct Context<'a> {
     size: u32,
     pad: u32,
+    /// Number of the lower-detail levels to dice.
+    lods: u32,
+    /// Alpha threshold and simplification tolerance of the outline, when enabled.
+    outline: Option<(u8, f32)>,
     /// Currently diced source sprite.
     sprite: &'a SourceSprite,
+    /// Padded pixels of the units diced so far, mapped by content hashes; shared by the units
+    /// with identical content, so that the pixels are stored once per unique hash.
+    pool: &'a mut HashMap<u64, Arc<[Pixel]>>,
 }
 
-fn new_ctx<'a>(sprite: &'a SourceSprite, prefs: &Prefs) -> Context<'a> {
+fn new_ctx<'a>(
+    sprite: &'a SourceSprite,
+    pool: &'a mut HashMap<u64, Arc<[Pixel]>>,
+    prefs: &Prefs,
+) -> Context<'a> {
     Context {
         size: prefs.unit_size,
         pad: prefs.padding,
+        lods: prefs.lod_count,
+        outline: prefs
+            .build_outline
+            .then_some((prefs.outline_threshold, prefs.outline_tolerance)),
         sprite,
+        pool,
     }
 }
 
-fn dice_it(ctx: &Context) -> Option<DicedTexture> {
-    let mut units = Vec::new();
-    let unit_count_x = ctx.sprite.texture.width.div_ceil(ctx.size);
-    let unit_count_y = ctx.sprite.texture.height.div_ceil(ctx.size);
-
-    for x in 0..unit_count_x {
-        for y in 0..unit_count_y {
-            if let Some(unit) = dice_at(x, y, ctx) {
-                units.push(unit);
-            }
-        }
-    }
-
+fn dice_it(ctx: &mut Context) -> Option<DicedTexture> {
+    let sprite = ctx.sprite;
+    let mut units = dice_units(&sprite.texture, 0, ctx);
     if units.is_empty() {
         return None;
     }
+    for lod in 1..=ctx.lods {
+        let downscaled = downscale(&sprite.texture, 1 << lod);
+        units.extend(dice_units(&downscaled, lod, ctx));
+    }
 
     Some(DicedTexture {
         id: ctx.sprite.id.to_owned(),
         size: USize::new(ctx.sprite.texture.width, ctx.sprite.texture.height),
         unique: units.iter().map(|u| u.hash).collect::<HashSet<_>>(),
         pivot: ctx.sprite.pivot.to_owned(),
+        origin: ctx.sprite.origin.to_owned(),
+        group: ctx.sprite.group.to_owned(),
+        ppu: ctx.sprite.ppu,
+        outline: match ctx.outline {
+            Some((threshold, tolerance)) => {
+                outline::trace(&ctx.sprite.texture, threshold, tolerance)
+            }
+            None => vec![],
+        },
+        border: ctx.sprite.border.to_owned(),
         units,
+        scale: 1,
+        whole: false,
+        aliases: vec![],
     })
 }
 
-fn dice_at(unit_x: u32, unit_y: u32, ctx: &Context) -> Option<DicedUnit> {
+fn dice_units(tex: &Texture, lod: u32, ctx: &mut Context) -> Vec<DicedUnit> {
+    let mut units = Vec::new();
+    let unit_count_x = tex.width.div_ceil(ctx.size);
+    let unit_count_y = tex.height.div_ceil(ctx.size);
+
+    for x in 0..unit_count_x {
+        for y in 0..unit_count_y {
+            if let Some(unit) = dice_at(x, y, tex, lod, ctx) {
+                units.push(unit);
+            }
+        }
+    }
+
+    units
+}
+
+fn dice_at(
+    unit_x: u32,
+    unit_y: u32,
+    tex: &Texture,
+    lod: u32,
+    ctx: &mut Context,
+) -> Option<DicedUnit> {
     let unit_rect = IRect {
         x: unit_x as i32 * ctx.size as i32,
         y: unit_y as i32 * ctx.size as i32,
@@ -73,16 +252,216 @@ fn dice_at(unit_x: u32, unit_y: u32, ctx: &Context) -> Option<DicedUnit> {
         height: ctx.size,
     };
 
-    let unit_pixels = get_pixels(&unit_rect, &ctx.sprite.texture);
+    let unit_pixels = get_pixels(&unit_rect, tex);
     if unit_pixels.iter().all(|p| p.a() == 0) {
         return None;
     }
 
     let hash = hash(&unit_pixels);
-    let rect = crop_over_borders(&unit_rect, &ctx.sprite.texture);
-    let padded_rect = pad_rect(&unit_rect, ctx.pad);
-    let pixels = get_pixels(&padded_rect, &ctx.sprite.texture);
-    Some(DicedUnit { rect, pixels, hash })
+    let rect = crop_over_borders(&unit_rect, tex);
+    let pixels = ctx.pool.entry(hash).or_insert_with(|| {
+        let padded_rect = pad_rect(&unit_rect, ctx.pad);
+        get_pixels(&padded_rect, tex).into()
+    });
+    let pixels = pixels.clone();
+    let size = USize::new(ctx.size + ctx.pad * 2, ctx.size + ctx.pad * 2);
+    Some(DicedUnit {
+        rect,
+        pixels,
+        size,
+        hash,
+        lod,
+    })
+}
+
+/// Replaces units of the textures with reuse ratio below the threshold with a single unit
+/// covering the trimmed texture rect.
+fn pack_low_reuse_whole(textures: &mut [DicedTexture], prefs: &Prefs) {
+    let mut counts = HashMap::new();
+    let full = |u: &&DicedUnit| u.lod == 0;
+    for unit in textures.iter().flat_map(|t| t.units.iter().filter(full)) {
+        *counts.entry(unit.hash).or_insert(0) += 1;
+    }
+    for texture in textures.iter_mut() {
+        let units: Vec<_> = texture.units.iter().filter(full).collect();
+        let reused = units.iter().filter(|u| counts[&u.hash] > 1).count();
+        let ratio = reused as f32 / units.len() as f32;
+        if ratio < prefs.reuse_threshold {
+            make_whole(texture, prefs);
+        }
+    }
+}
+
+/// Re-dices full-detail units of specified texture with twice the current unit size, so that
+/// the mesh has about 4 times fewer quads, while the larger units can still be reused. When
+/// a unit would cover the entire texture, the texture is packed whole instead. Returns false
+/// when the texture is already whole and can't be coarsened any further. The pixels of the
+/// larger units are stored in specified pool, which is to be shared between the calls.
+pub(crate) fn coarsen(
+    texture: &mut DicedTexture,
+    pool: &mut HashMap<u64, Arc<[Pixel]>>,
+    prefs: &Prefs,
+) -> bool {
+    if texture.whole {
+        return false;
+    }
+    texture.scale *= 2;
+    let size = prefs.unit_size * texture.scale;
+    if size >= texture.size.width.max(texture.size.height) {
+        make_whole(texture, prefs);
+        return true;
+    }
+    let (source, _) = assemble(texture, prefs);
+    let sprite = SourceSprite {
+        id: texture.id.to_owned(),
+        texture: source,
+        pivot: None,
+        origin: None,
+        group: None,
+        ppu: None,
+        border: None,
+    };
+    let mut ctx = Context {
+        size,
+        pad: prefs.padding,
+        lods: 0,
+        outline: None,
+        sprite: &sprite,
+        pool,
+    };
+    let mut units = dice_units(&sprite.texture, 0, &mut ctx);
+    units.extend(texture.units.drain(..).filter(|u| u.lod > 0));
+    texture.unique = units.iter().map(|u| u.hash).collect();
+    texture.units = units;
+    true
+}
+
+fn make_whole(texture: &mut DicedTexture, prefs: &Prefs) {
+    let unit = redice_whole(texture, prefs);
+    texture.unique = HashSet::from([unit.hash]);
+    texture.units = vec![unit];
+    texture.whole = true;
+}
+
+/// Re-assembles texture from the diced units and chops the opaque area as a single unit.
+/// Both the assembled and original textures contain identical pixels, as units with same
+/// hashes have same content and the units omitted while dicing are fully transparent.
+fn redice_whole(texture: &DicedTexture, prefs: &Prefs) -> DicedUnit {
+    let (source, _) = assemble(texture, prefs);
+    chop_whole(&source, prefs.padding)
+}
+
+/// Whether specified diced texture was diced from pixels identical to the source texture.
+/// The transparent units are not diced, hence the source only has to be transparent there.
+fn same_pixels(texture: &DicedTexture, source: &Texture, prefs: &Prefs) -> bool {
+    if texture.size.width != source.width || texture.size.height != source.height {
+        return false;
+    }
+    let (diced, covered) = assemble(texture, prefs);
+    let mut pixels = diced.pixels.iter().zip(&source.pixels).zip(covered);
+    pixels.all(|((diced, source), covered)| match covered {
+        true => diced == source,
+        false => source.a() == 0,
+    })
+}
+
+/// Reassembles source texture of the specified diced texture from its full-detail units,
+/// which may be of any size (eg, coarsened or whole); also returns whether each pixel is
+/// covered by a unit.
+fn assemble(texture: &DicedTexture, prefs: &Prefs) -> (Texture, Vec<bool>) {
+    let size = &texture.size;
+    let len = (size.width * size.height) as usize;
+    let mut source = Texture {
+        width: size.width,
+        height: size.height,
+        pixels: vec![Pixel::default(); len],
+    };
+    let mut covered = vec![false; len];
+    for unit in texture.units.iter().filter(|u| u.lod == 0) {
+        let stride = unit.size.width as usize;
+        for y in 0..unit.rect.height {
+            let from = (y + prefs.padding) as usize * stride + prefs.padding as usize;
+            let into = ((unit.rect.y + y) * size.width + unit.rect.x) as usize;
+            let width = unit.rect.width as usize;
+            source.pixels[into..into + width].copy_from_slice(&unit.pixels[from..from + width]);
+            covered[into..into + width].fill(true);
+        }
+    }
+    (source, covered)
+}
+
+/// Chops the opaque area of specified texture as a single padded unit.
+fn chop_whole(source: &Texture, pad: u32) -> DicedUnit {
+    let rect = trim_rect(source);
+    let pixels = get_pixels(&rect, source);
+    let hash = hash_whole(rect.width, &pixels);
+    let padded_rect = pad_rect(&rect, pad);
+    let pixels = get_pixels(&padded_rect, source).into();
+    DicedUnit {
+        rect: URect::new(rect.x as u32, rect.y as u32, rect.width, rect.height),
+        pixels,
+        size: USize::new(padded_rect.width, padded_rect.height),
+        hash,
+        lod: 0,
+    }
+}
+
+/// Downscales texture by specified factor, averaging each block of pixels; colors are weighted
+/// by alpha, so that transparent pixels don't darken the edges.
+fn downscale(tex: &Texture, factor: u32) -> Texture {
+    let width = tex.width.div_ceil(factor);
+    let height = tex.height.div_ceil(factor);
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (mut r, mut g, mut b, mut a, mut count) = (0, 0, 0, 0, 0);
+            for sy in y * factor..((y + 1) * factor).min(tex.height) {
+                for sx in x * factor..((x + 1) * factor).min(tex.width) {
+                    let pixel = &tex.pixels[(sx + sy * tex.width) as usize];
+                    let alpha = pixel.a() as u64;
+                    r += pixel.r() as u64 * alpha;
+                    g += pixel.g() as u64 * alpha;
+                    b += pixel.b() as u64 * alpha;
+                    a += alpha;
+                    count += 1;
+                }
+            }
+            pixels.push(match a {
+                0 => Pixel::default(),
+                _ => Pixel::new(
+                    (r / a) as u8,
+                    (g / a) as u8,
+                    (b / a) as u8,
+                    (a / count) as u8,
+                ),
+            });
+        }
+    }
+    Texture {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// Evaluates rect of the opaque pixels inside specified texture.
+fn trim_rect(tex: &Texture) -> IRect {
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (tex.width, tex.height, 0, 0);
+    for (idx, pixel) in tex.pixels.iter().enumerate() {
+        if pixel.a() > 0 {
+            let (x, y) = (idx as u32 % tex.width, idx as u32 / tex.width);
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+    IRect {
+        x: min_x as i32,
+        y: min_y as i32,
+        width: max_x + 1 - min_x,
+        height: max_y + 1 - min_y,
+    }
 }
 
 fn get_pixels(rect: &IRect, tex: &Texture) -> Vec<Pixel> {
@@ -124,10 +503,40 @@ fn crop_over_borders(rect: &IRect, tex: &Texture) -> URect {
     }
 }
 
+/// Tags of the hashed content kinds; regular units and whole textures share the cells mapped
+/// by the hashes, hence the kind and length prefix the pixels to keep the hashed bytes distinct.
+const UNIT_TAG: u8 = 0;
+const WHOLE_TAG: u8 = 1;
+
 fn hash(pixels: &[Pixel]) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    pixels.hash(&mut hasher);
-    hasher.finish()
+    let bytes = pixels.iter().flat_map(|p| p.to_raw());
+    fnv(tag(UNIT_TAG, pixels.len()).chain(bytes))
+}
+
+/// Unlike units, whole textures have varying dimensions, hence accounting the width as well.
+fn hash_source(sprite: &SourceSprite) -> u64 {
+    hash_whole(sprite.texture.width, &sprite.texture.pixels)
+}
+
+fn hash_whole(width: u32, pixels: &[Pixel]) -> u64 {
+    let bytes = pixels.iter().flat_map(|p| p.to_raw());
+    let tag = tag(WHOLE_TAG, pixels.len()).chain(width.to_le_bytes());
+    fnv(tag.chain(bytes))
+}
+
+fn tag(kind: u8, len: usize) -> impl Iterator<Item = u8> {
+    std::iter::once(kind).chain((len as u64).to_le_bytes())
+}
+
+fn fnv(bytes: impl Iterator<Item = u8>) -> u64 {
+    // FNV-1a: unlike the std hashers, it's guaranteed to produce identical results across
+    // Rust releases, which is required for the hashes persisted in atlas layouts to stay valid.
+    let mut hash = 0xcbf29ce484222325_u64;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
 }
 
 fn saturate(n: i32, max: u32) -> u32 {
@@ -142,9 +551,11 @@ fn saturate(n: i32, max: u32) -> u32 {
 
 #[cfg(test)]
 mod tests {
-    use crate::dicer::dice;
+    use crate::dicer::{coarsen, dice, hash, hash_whole, same_pixels};
     use crate::fixtures::*;
     use crate::models::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
 
     #[test]
     fn can_dice_with_defaults() {
@@ -231,6 +642,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn content_hash_is_stable() {
+        assert_eq!(dice1(&R1X1, 1, 0).units[0].hash, 0x6896ff027d3dc15c);
+    }
+
+    #[test]
+    fn content_hash_of_unit_and_whole_texture_with_same_bytes_is_not_equal() {
+        let whole = vec![R; 16 * 3];
+        let mut unit = vec![Pixel::new(16, 0, 0, 0)];
+        unit.extend_from_slice(&whole);
+        assert_ne!(hash(&unit), hash_whole(16, &whole));
+    }
+
     #[test]
     fn unit_rects_are_mapped_top_left_to_bottom_right() {
         let units = &dice(&[src(&RGBY)], &pref(1, 0)).unwrap()[0].units;
@@ -247,7 +671,7 @@ mod tests {
     fn when_no_content_padded_pixels_are_repeated() {
         #[rustfmt::skip]
         assert_eq!(
-            dice1(&B1X1, 1, 1).units[0].pixels,
+            dice1(&B1X1, 1, 1).units[0].pixels.to_vec(),
             vec![B, B, B,
                  B, B, B,
                  B, B, B]);
@@ -258,7 +682,7 @@ mod tests {
         let pixels = dice1(&BGRT, 1, 1)
             .units
             .into_iter()
-            .map(|u| u.pixels)
+            .map(|u| u.pixels.to_vec())
             .collect::<Vec<_>>();
         #[rustfmt::skip]
         assert!(pixels.contains(&vec![
@@ -267,6 +691,36 @@ mod tests {
             R, R, T]));
     }
 
+    #[test]
+    fn identical_units_share_pixels() {
+        let units = dice1(&RGB4X4, 1, 1).units;
+        let blue = units
+            .iter()
+            .filter(|u| u.pixels[4] == B)
+            .collect::<Vec<_>>();
+        assert!(blue.len() > 1);
+        assert!(blue.iter().all(|u| Arc::ptr_eq(&u.pixels, &blue[0].pixels)));
+    }
+
+    #[test]
+    fn pixels_are_stored_once_per_unique_unit() {
+        let tex = Texture {
+            width: 512,
+            height: 512,
+            pixels: (0..512 * 512).map(|i| [R, G][i % 512 / 256]).collect(),
+        };
+        let diced = dice(&[src(&tex), src(&tex)], &pref(8, 2)).unwrap();
+        let units = diced.iter().flat_map(|t| t.units.iter());
+        let mut stored = HashMap::new();
+        for unit in units.clone() {
+            stored.insert(Arc::as_ptr(&unit.pixels), unit.pixels.len());
+        }
+        let stored = stored.values().sum::<usize>();
+        let total = units.map(|u| u.pixels.len()).sum::<usize>();
+        assert_eq!(total, 2 * 64 * 64 * 12 * 12);
+        assert!(stored * 100 < total);
+    }
+
     #[test]
     fn diced_texture_contains_identical_units() {
         assert_eq!(16, dice1(&RGB4X4, 1, 0).units.len());
@@ -279,6 +733,188 @@ mod tests {
         assert_eq!(16, dice1(&PLT4X4, 1, 0).unique.len());
     }
 
+    #[test]
+    fn streamed_sources_are_diced_same_as_slice() {
+        let sources = [src(&RGBY), src(&BGRT), src(&RGB4X4)];
+        let stream = [&RGBY, &BGRT, &RGB4X4].into_iter().map(|t| src(t));
+        let streamed = super::dice_stream(stream, &pref(1, 1)).unwrap();
+        let diced = dice(&sources, &pref(1, 1)).unwrap();
+        assert_eq!(streamed.len(), diced.len());
+        for (streamed, diced) in streamed.iter().zip(diced.iter()) {
+            assert_eq!(streamed.unique, diced.unique);
+        }
+    }
+
+    #[test]
+    fn textures_with_low_reuse_are_diced_whole() {
+        let diced = dice_hybrid(&[&PLT3X3, &RGB4X4], 1, 1);
+        assert!(diced[0].whole);
+        assert_eq!(diced[0].units.len(), 1);
+        assert_eq!(diced[0].units[0].rect, URect::new(0, 0, 3, 3));
+        assert_eq!(diced[0].units[0].size, USize::new(5, 5));
+        assert!(!diced[1].whole);
+    }
+
+    #[test]
+    fn coarsened_textures_are_diced_with_larger_units() {
+        let mut diced = dice1(&RGB4X4, 1, 1);
+        assert!(coarsen(&mut diced, &mut HashMap::new(), &pref(1, 1)));
+        assert!(!diced.whole);
+        assert_eq!(diced.scale, 2);
+        assert_eq!(diced.units.len(), 4);
+        assert!(diced.units.iter().all(|u| u.size == USize::new(4, 4)));
+        assert!(same_pixels(&diced, &RGB4X4, &pref(1, 1)));
+    }
+
+    #[test]
+    fn textures_are_coarsened_whole_as_last_resort() {
+        let (mut diced, mut pool) = (dice1(&RGB4X4, 1, 0), HashMap::new());
+        assert!(coarsen(&mut diced, &mut pool, &pref(1, 0)));
+        assert!(coarsen(&mut diced, &mut pool, &pref(1, 0)));
+        assert!(diced.whole);
+        assert_eq!(diced.units.len(), 1);
+        assert!(!coarsen(&mut diced, &mut pool, &pref(1, 0)));
+    }
+
+    #[test]
+    fn coarsened_units_with_identical_content_share_pixels() {
+        let mut diced = dice(&[src(&RGB4X4), src(&RGB4X4)], &pref(1, 0)).unwrap();
+        let mut pool = HashMap::new();
+        for texture in diced.iter_mut() {
+            assert!(coarsen(texture, &mut pool, &pref(1, 0)));
+        }
+        let (a, b) = (&diced[0].units[0].pixels, &diced[1].units[0].pixels);
+        assert!(Arc::ptr_eq(a, b));
+    }
+
+    #[test]
+    fn errs_when_vertex_budget_is_set_without_quads() {
+        let prefs = Prefs {
+            vertex_budget: 4,
+            build_quads: false,
+            build_instances: true,
+            ..pref(1, 0)
+        };
+        let result = dice(&[src(&RGB4X4)], &prefs);
+        assert!(result.is_err_and(|e| e.to_string() == "Vertex budget requires building quads."));
+    }
+
+    #[test]
+    fn duplicates_are_not_aliased_by_default() {
+        let diced = dice(&[src(&RGB4X4), src(&RGB4X4)], &pref(1, 0)).unwrap();
+        assert_eq!(diced.len(), 2);
+        assert!(diced.iter().all(|t| t.aliases.is_empty()));
+    }
+
+    #[test]
+    fn duplicates_are_aliased_when_enabled() {
+        let prefs = Prefs {
+            alias_duplicates: true,
+            ..pref(1, 0)
+        };
+        let copy = SourceSprite {
+            id: "copy".to_owned(),
+            ..src(&RGB4X4)
+        };
+        let diced = dice(&[src(&RGB4X4), src(&BGRT), copy], &prefs).unwrap();
+        assert_eq!(diced.len(), 2);
+        assert_eq!(diced[0].aliases, vec!["copy".to_owned()]);
+        assert!(diced[1].aliases.is_empty());
+    }
+
+    #[test]
+    fn duplicates_with_distinct_settings_are_not_aliased() {
+        let prefs = Prefs {
+            alias_duplicates: true,
+            ..pref(1, 0)
+        };
+        let pivoted = SourceSprite {
+            pivot: Some(Pivot::new(0.5, 0.5)),
+            ..src(&RGB4X4)
+        };
+        let diced = dice(&[src(&RGB4X4), pivoted], &prefs).unwrap();
+        assert_eq!(diced.len(), 2);
+    }
+
+    #[test]
+    fn pixels_are_compared_before_aliasing() {
+        let prefs = pref(2, 0);
+        let diced = dice(&[src(&RGB4X4), src(&BGRT)], &prefs).unwrap();
+        assert!(same_pixels(&diced[0], &RGB4X4, &prefs));
+        assert!(!same_pixels(&diced[0], &BGRT, &prefs));
+        assert!(same_pixels(&diced[1], &BGRT, &prefs));
+    }
+
+    #[test]
+    fn whole_unit_rect_is_trimmed() {
+        let diced = dice_hybrid(&[&BTGT], 1, 0);
+        assert_eq!(diced[0].units[0].rect, URect::new(0, 0, 1, 2));
+    }
+
+    #[test]
+    fn whole_unit_pixels_are_padded_with_neighbors() {
+        let diced = dice_hybrid(&[&TTTM], 1, 1);
+        #[rustfmt::skip]
+        assert_eq!(diced[0].units[0].pixels.to_vec(), vec![
+            T, T, T,
+            T, M, M,
+            T, M, M]);
+    }
+
+    #[test]
+    fn whole_unit_hash_accounts_dimensions() {
+        let vertical = dice_hybrid(&[&RGB1X3], 1, 0).pop().unwrap();
+        let horizontal = dice_hybrid(&[&RGB3X1], 1, 0).pop().unwrap();
+        assert_ne!(vertical.units[0].hash, horizontal.units[0].hash);
+    }
+
+    #[test]
+    fn errs_when_lod_count_is_above_16() {
+        let prefs = Prefs {
+            lod_count: 17,
+            ..pref(1, 0)
+        };
+        assert!(
+            dice(&[src(&R1X1)], &prefs)
+                .is_err_and(|e| e.to_string() == "LOD count can't be above 16.")
+        );
+    }
+
+    #[test]
+    fn lod_units_are_not_diced_by_default() {
+        assert!(dice1(&RGB4X4, 1, 0).units.iter().all(|u| u.lod == 0));
+    }
+
+    #[test]
+    fn lod_units_are_diced_from_downscaled_texture() {
+        let prefs = Prefs {
+            lod_count: 2,
+            ..pref(1, 0)
+        };
+        let diced = dice(&[src(&RGB4X4)], &prefs).unwrap().pop().unwrap();
+        let count = |lod: u32| diced.units.iter().filter(|u| u.lod == lod).count();
+        assert_eq!(count(0), 16);
+        assert_eq!(count(1), 4);
+        assert_eq!(count(2), 1);
+        let mut lod = diced.units.iter().filter(|u| u.lod == 1);
+        assert!(lod.clone().all(|u| u.rect.x < 2 && u.rect.y < 2));
+        assert!(lod.all(|u| diced.unique.contains(&u.hash)));
+    }
+
+    #[test]
+    fn downscaled_colors_are_weighted_by_alpha() {
+        let tex = Texture {
+            width: 3,
+            height: 2,
+            pixels: vec![R, T, B, R, R, B],
+        };
+        let downscaled = super::downscale(&tex, 2);
+        assert_eq!(downscaled.width, 2);
+        assert_eq!(downscaled.height, 1);
+        assert_eq!(downscaled.pixels[0], Pixel::new(255, 0, 0, 191));
+        assert_eq!(downscaled.pixels[1], B);
+    }
+
     #[test]
     fn reports_progress() {
         let progress = sample_progress(|p| drop(dice(&[src(&B1X1)], &p)));
@@ -290,6 +926,15 @@ mod tests {
         dice(&[src(tex)], &pref).unwrap().pop().unwrap()
     }
 
+    fn dice_hybrid(src: &[&Texture], size: u32, pad: u32) -> Vec<DicedTexture> {
+        let prefs = Prefs {
+            reuse_threshold: 0.5,
+            ..pref(size, pad)
+        };
+        let sprites = src.iter().map(|t| self::src(t)).collect::<Vec<_>>();
+        dice(&sprites, &prefs).unwrap()
+    }
+
     fn pref(size: u32, pad: u32) -> Prefs {
         Prefs {
             unit_size: size,
@@ -303,6 +948,10 @@ mod tests {
             id: "test".to_string(),
             texture: tex.to_owned(),
             pivot: None,
+            origin: None,
+            group: None,
+            ppu: None,
+            border: None,
         }
     }
 
@@ -30,6 +30,11 @@ pub static BGRT: LazyLock<Texture> = LazyLock::new(|| tex(2, 2, vec![
     R, T
 ]));
 #[rustfmt::skip]
+pub static CMYT: LazyLock<Texture> = LazyLock::new(|| tex(2, 2, vec![
+    C, M,
+    Y, T
+]));
+#[rustfmt::skip]
 pub static BTGR: LazyLock<Texture> = LazyLock::new(|| tex(2, 2, vec![
     B, T,
     G, R
@@ -76,6 +81,7 @@ pub static RGB4X4: LazyLock<Texture> = LazyLock::new(|| tex(4, 4, vec![
     R, G, B, R,
     B, B, R, G,
 ]));
+pub static PLT3X3: LazyLock<Texture> = LazyLock::new(|| palette(3, 3));
 pub static PLT4X4: LazyLock<Texture> = LazyLock::new(|| palette(4, 4));
 
 pub fn sample_progress(act: impl Fn(Prefs)) -> Progress {
@@ -92,11 +98,27 @@ pub fn sample_progress(act: impl Fn(Prefs)) -> Progress {
 pub trait AnySource {
     fn texture(&self) -> Texture;
     fn pivot(&self) -> Option<Pivot>;
+    fn group(&self) -> Option<String> {
+        None
+    }
+    fn ppu(&self) -> Option<f32> {
+        None
+    }
+    fn origin(&self) -> Option<Origin> {
+        None
+    }
+    fn border(&self) -> Option<Border> {
+        None
+    }
     fn sprite(&self) -> SourceSprite {
         SourceSprite {
             id: "TEST".to_string(),
             texture: self.texture(),
             pivot: self.pivot(),
+            origin: self.origin(),
+            group: self.group(),
+            ppu: self.ppu(),
+            border: self.border(),
         }
     }
 }
@@ -122,6 +144,54 @@ impl AnySource for (&LazyLock<Texture>, (f32, f32)) {
     }
 }
 
+impl AnySource for (&LazyLock<Texture>, &str) {
+    fn texture(&self) -> Texture {
+        (self.0 as &Texture).to_owned()
+    }
+    fn pivot(&self) -> Option<Pivot> {
+        None
+    }
+    fn group(&self) -> Option<String> {
+        Some(self.1.to_owned())
+    }
+}
+
+impl AnySource for (&LazyLock<Texture>, f32) {
+    fn texture(&self) -> Texture {
+        (self.0 as &Texture).to_owned()
+    }
+    fn pivot(&self) -> Option<Pivot> {
+        None
+    }
+    fn ppu(&self) -> Option<f32> {
+        Some(self.1)
+    }
+}
+
+impl AnySource for (&LazyLock<Texture>, Origin) {
+    fn texture(&self) -> Texture {
+        (self.0 as &Texture).to_owned()
+    }
+    fn pivot(&self) -> Option<Pivot> {
+        None
+    }
+    fn origin(&self) -> Option<Origin> {
+        Some(self.1.to_owned())
+    }
+}
+
+impl AnySource for (&LazyLock<Texture>, Border) {
+    fn texture(&self) -> Texture {
+        (self.0 as &Texture).to_owned()
+    }
+    fn pivot(&self) -> Option<Pivot> {
+        None
+    }
+    fn border(&self) -> Option<Border> {
+        Some(self.1.to_owned())
+    }
+}
+
 fn tex(width: u32, height: u32, pixels: Vec<Pixel>) -> Texture {
     Texture {
         width,
@@ -1,10 +1,15 @@
+mod buffer;
 mod builder;
 mod dicer;
 mod fixtures;
+mod library;
+mod maxrects;
 mod models;
+mod outline;
 mod packer;
 
 pub use models::*;
+use std::collections::HashMap;
 
 /// Splits specified sprite textures into chunks, discards identical ones, joins unique
 /// chunks into atlas textures and generates sprite meshes with texture coordinates mapped
@@ -34,8 +39,8 @@ pub use models::*;
 ///
 /// // Collect source sprites to dice.
 /// let sprites = vec![
-///     SourceSprite { id: "1".to_owned(), texture: load("1.png"), pivot: None },
-///     SourceSprite { id: "2".to_owned(), texture: load("2.png"), pivot: None },
+///     SourceSprite { id: "1".to_owned(), texture: load("1.png"), pivot: None, origin: None, group: None, ppu: None, border: None },
+///     SourceSprite { id: "2".to_owned(), texture: load("2.png"), pivot: None, origin: None, group: None, ppu: None, border: None },
 ///     // ...
 /// ];
 ///
@@ -66,8 +71,218 @@ pub use models::*;
 /// ```
 pub fn dice(sprites: &[SourceSprite], prefs: &Prefs) -> Result<Artifacts> {
     let diced = dicer::dice(sprites, prefs)?;
-    let packed = packer::pack(diced, prefs)?;
+    let pack = |diced| packer::pack(diced, prefs).map(|p| (p, ()));
+    let (packed, _, sprites) = pack_and_build(diced, prefs, pack)?;
+    Ok(to_artifacts(packed, sprites, prefs))
+}
+
+/// Same as [dice], but accepts source sprites from an iterator, dicing them one at a time.
+/// Each source texture is dropped right after it's diced, while only the pixels of the unique
+/// units are retained, allowing to process sprite sets that don't fit in memory when decoded
+/// up front, as long as the iterator decodes the textures lazily. The iterator may fail
+/// supplying a sprite (eg, when decoding the texture), in which case the operation stops
+/// and the error is returned without packing the sprites diced so far.
+///
+/// # Arguments
+///
+/// * `sprites`: Iterator supplying source sprite textures to dice or errors to stop with.
+/// * `prefs`: User preferences for the dicing operation.
+///
+/// returns: Generated atlas textures and diced sprite meshes or the error.
+pub fn dice_stream<E: From<Error>>(
+    sprites: impl IntoIterator<Item = std::result::Result<SourceSprite, E>>,
+    prefs: &Prefs,
+) -> std::result::Result<Artifacts, E> {
+    let mut error = None;
+    let sources = sprites
+        .into_iter()
+        .map_while(|s| s.map_err(|e| error = Some(e)).ok());
+    let diced = dicer::dice_stream(sources, prefs);
+    if let Some(err) = error {
+        return Err(err);
+    }
+    let pack = |diced| packer::pack(diced, prefs).map(|p| (p, ()));
+    let (packed, _, sprites) = pack_and_build(diced?, prefs, pack)?;
+    Ok(to_artifacts(packed, sprites, prefs))
+}
+
+/// Dices specified sprite textures and packs the units into atlases produced by a previous
+/// dicing operation. Units already packed into the atlases keep their positions; new units
+/// are written into free cells of the atlases or, when there is no room, into new atlases.
+/// With [Prefs::atlas_uniform], the new atlases are expanded to the dimensions of the previous
+/// atlases of the same group, failing when they exceed the previous dimensions.
+///
+/// # Arguments
+///
+/// * `previous`: Artifacts produced by a previous dicing or appending operation.
+/// * `sprites`: New source sprite textures to dice and append.
+/// * `prefs`: User preferences for the dicing operation; expected to be the same as used to
+///   produce the previous artifacts.
+///
+/// returns: All the atlases (unchanged, modified and new) with diced sprites built for the
+/// specified sources only, along with indexes of the modified and new atlases; or [Error].
+pub fn append(previous: &Artifacts, sprites: &[SourceSprite], prefs: &Prefs) -> Result<Appended> {
+    let diced = dicer::dice(sprites, prefs)?;
+    let pack = |diced| packer::append(diced, previous, prefs);
+    let (packed, changed, sprites) = pack_and_build(diced, prefs, pack)?;
+    let artifacts = to_artifacts(packed, sprites, prefs);
+    Ok(Appended { artifacts, changed })
+}
+
+/// Dices specified sprite textures and packs only the units absent in a shipped release into
+/// new atlases, allowing to deliver the new pixel content as a delta patch. Built sprite meshes
+/// reference units of both the shipped and the new atlases.
+///
+/// # Arguments
+///
+/// * `shipped`: Layouts of the atlases in the shipped release, as produced by [dice] or [append].
+/// * `sprites`: Source sprite textures to dice; can include both new and shipped sprites.
+/// * `prefs`: User preferences for the dicing operation; expected to be the same as used to
+///   produce the shipped atlases.
+///
+/// returns: New atlases and their layouts with diced sprites built for the specified sources
+/// or [Error]. Atlas indexes of the sprites below the number of shipped layouts refer to the
+/// shipped atlases, while the rest refer to the returned atlases offset by the shipped count.
+pub fn patch(shipped: &[Layout], sprites: &[SourceSprite], prefs: &Prefs) -> Result<Artifacts> {
+    let diced = dicer::dice(sprites, prefs)?;
+    let pack = |diced| packer::patch(diced, shipped, prefs).map(|p| (p, ()));
+    let (mut packed, _, sprites) = pack_and_build(diced, prefs, pack)?;
+    let aliases = collect_aliases(&packed);
+    let new = packed.split_off(shipped.len());
+    Ok(Artifacts {
+        aliases,
+        ..to_artifacts(new, sprites, prefs)
+    })
+}
+
+/// Dices specified sprite textures against a library of base units (eg, a shared UI kit).
+/// Units present in the library are referenced instead of being packed again, so that the
+/// produced atlases contain only the units absent in the library.
+///
+/// # Arguments
+///
+/// * `library`: Library of the base units, created with [Library::new] or deserialized with
+///   [Library::from_bytes]; unit size, padding and UV inset should match the preferences.
+/// * `sprites`: Source sprite textures to dice.
+/// * `prefs`: User preferences for the dicing operation.
+///
+/// returns: Atlases with the units absent in the library and diced sprites or [Error].
+/// Same as with [patch], atlas indexes of the sprites below the number of library layouts
+/// refer to the library atlases, while the rest refer to the returned atlases offset by
+/// the number of library layouts.
+pub fn dice_with_library(
+    library: &Library,
+    sprites: &[SourceSprite],
+    prefs: &Prefs,
+) -> Result<Artifacts> {
+    if library.unit_size != prefs.unit_size
+        || library.padding != prefs.padding
+        || library.uv_inset != prefs.uv_inset
+    {
+        return Err(Error::Spec(
+            "Library doesn't match unit size, padding and UV inset.",
+        ));
+    }
+    patch(&library.layouts, sprites, prefs)
+}
+
+/// Packs specified sprite textures into atlases as whole, without dicing, using the MaxRects
+/// bin-packing algorithm. Each sprite is built as a single quad mapped to its trimmed area,
+/// allowing to compare the results against [dice] with the same preferences.
+///
+/// Atlas size limit, square, POT, uniform and padding preferences are respected, while the
+/// dicing-specific ones (unit size, UV inset, reuse threshold, balance, LOD count and vertex
+/// budget) are ignored, though still validated the same as with [dice].
+/// Identical sprites share the packed area. Returned layouts only describe dimensions of
+/// the atlases and have no cell grid, hence [append], [patch] and [Library::new] reject
+/// the artifacts with [Error::Spec].
+///
+/// # Arguments
+///
+/// * `sprites`: Source sprite textures to pack.
+/// * `prefs`: User preferences for the packing operation.
+///
+/// returns: Generated atlas textures and sprite meshes or [Error].
+pub fn pack(sprites: &[SourceSprite], prefs: &Prefs) -> Result<Artifacts> {
+    let diced = dicer::dice_whole(sprites, prefs)?;
+    let packed = maxrects::pack(diced, prefs)?;
     let sprites = builder::build(&packed, prefs)?;
+    let layouts = packed.iter().map(maxrects::layout).collect();
+    let aliases = collect_aliases(&packed);
+    let atlases = packed.into_iter().map(|p| p.texture).collect();
+    Ok(Artifacts {
+        atlases,
+        layouts,
+        sprites,
+        aliases,
+    })
+}
+
+/// Writes meshes of specified diced sprites into an interleaved vertex buffer with specified
+/// layout, along with an index buffer, allowing to upload the data to GPU directly. Sprites
+/// are written one after another, so a batch of sprites can be drawn with a single call.
+///
+/// # Arguments
+///
+/// * `sprites`: Diced sprites to write; use [std::slice::from_ref] to write a single sprite.
+/// * `layout`: Placement and format of the vertex attributes.
+///
+/// returns: Vertex and index buffers with ranges of each sprite or [Error].
+pub fn write_vertex_buffer(sprites: &[DicedSprite], layout: &VertexLayout) -> Result<VertexBuffer> {
+    buffer::write(sprites, layout)
+}
+
+/// Packs diced textures with specified function and builds the sprites. When meshes of some
+/// sprites exceed [Prefs::vertex_budget], their textures are re-diced with larger units (or
+/// whole, as the last resort) and all the textures are packed and built again.
+fn pack_and_build<T>(
+    mut diced: Vec<models::DicedTexture>,
+    prefs: &Prefs,
+    pack: impl Fn(Vec<models::DicedTexture>) -> Result<(Vec<models::Atlas>, T)>,
+) -> Result<(Vec<models::Atlas>, T, Vec<DicedSprite>)> {
+    let budget = prefs.vertex_budget as usize;
+    let mut pool = HashMap::new();
+    loop {
+        let (packed, extra) = pack(diced)?;
+        let sprites = builder::build(&packed, prefs)?;
+        let over: Vec<_> = match budget {
+            0 => vec![],
+            _ => (0..sprites.len())
+                .filter(|idx| sprites[*idx].vertices.len() > budget)
+                .collect(),
+        };
+        if over.is_empty() {
+            return Ok((packed, extra, sprites));
+        }
+        // Sprites are built in the order of the textures packed into the atlases.
+        diced = packed.into_iter().flat_map(|a| a.packed).collect();
+        for idx in over {
+            if !dicer::coarsen(&mut diced[idx], &mut pool, prefs) {
+                return Err(Error::Budget(sprites[idx].id.to_owned()));
+            }
+        }
+    }
+}
+
+fn to_artifacts(packed: Vec<models::Atlas>, sprites: Vec<DicedSprite>, prefs: &Prefs) -> Artifacts {
+    let layouts = packed.iter().map(|a| packer::layout(a, prefs)).collect();
+    let aliases = collect_aliases(&packed);
     let atlases = packed.into_iter().map(|p| p.texture).collect();
-    Ok(Artifacts { atlases, sprites })
+    Artifacts {
+        atlases,
+        layouts,
+        sprites,
+        aliases,
+    }
+}
+
+fn collect_aliases(packed: &[models::Atlas]) -> Vec<Alias> {
+    let textures = packed.iter().flat_map(|a| a.packed.iter());
+    let aliases = textures.flat_map(|t| {
+        t.aliases.iter().map(|id| Alias {
+            id: id.to_owned(),
+            target: t.id.to_owned(),
+        })
+    });
+    aliases.collect()
 }
@@ -0,0 +1,292 @@
+//! Serialization of the unit library.
+
+use crate::models::*;
+use crate::packer;
+use std::collections::HashMap;
+
+/// Signature of the serialized library data.
+const MAGIC: &[u8; 4] = b"SDLB";
+/// Version of the serialized library format.
+const VERSION: u8 = 1;
+
+impl Library {
+    /// Creates library of the units packed into specified artifacts, which were produced
+    /// with specified preferences. Returns [Error] when the layouts don't match the
+    /// preferences or were produced with [crate::pack].
+    pub fn new(artifacts: &Artifacts, prefs: &Prefs) -> Result<Self> {
+        let padded_unit_size = prefs.unit_size + prefs.padding * 2;
+        for layout in artifacts.layouts.iter() {
+            packer::check_layout(layout, padded_unit_size)?;
+        }
+        Ok(Library {
+            unit_size: prefs.unit_size,
+            padding: prefs.padding,
+            uv_inset: prefs.uv_inset,
+            layouts: artifacts.layouts.to_owned(),
+        })
+    }
+
+    /// Serializes the library into a compact little-endian binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        write_u32(&mut bytes, self.unit_size);
+        write_u32(&mut bytes, self.padding);
+        write_u32(&mut bytes, self.uv_inset.to_bits());
+        write_u32(&mut bytes, self.layouts.len() as u32);
+        for layout in self.layouts.iter() {
+            write_layout(&mut bytes, layout);
+        }
+        bytes
+    }
+
+    /// Deserializes library previously serialized with [Library::to_bytes].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC || reader.take(1)?[0] != VERSION {
+            return Err(Error::Spec("Library data is not recognized."));
+        }
+        let unit_size = reader.u32()?;
+        let padding = reader.u32()?;
+        let uv_inset = f32::from_bits(reader.u32()?);
+        let padded_unit_size = padding
+            .checked_mul(2)
+            .and_then(|p| p.checked_add(unit_size))
+            .filter(|_| (0.0..=0.5).contains(&uv_inset))
+            .ok_or(Error::Spec("Library data is corrupted."))?;
+        let count = reader.u32()?;
+        let mut layouts = vec![];
+        for _ in 0..count {
+            let layout = read_layout(&mut reader)?;
+            packer::check_layout(&layout, padded_unit_size)
+                .map_err(|_| Error::Spec("Library data is corrupted."))?;
+            layouts.push(layout);
+        }
+        if reader.pos != bytes.len() {
+            return Err(Error::Spec("Library data is corrupted."));
+        }
+        Ok(Library {
+            unit_size,
+            padding,
+            uv_inset,
+            layouts,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let end = self.pos.checked_add(len).filter(|e| *e <= self.bytes.len());
+        let end = end.ok_or(Error::Spec("Library data is corrupted."))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+fn write_layout(bytes: &mut Vec<u8>, layout: &Layout) {
+    match &layout.group {
+        Some(group) => {
+            bytes.push(1);
+            write_u32(bytes, group.len() as u32);
+            bytes.extend_from_slice(group.as_bytes());
+        }
+        None => bytes.push(0),
+    }
+    write_u32(bytes, layout.width);
+    write_u32(bytes, layout.height);
+    write_u32(bytes, layout.columns);
+    write_u32(bytes, layout.rows);
+    write_u32(bytes, layout.cells.len() as u32);
+    // Sorting by cells to keep the output deterministic.
+    let mut cells = layout.cells.iter().collect::<Vec<_>>();
+    cells.sort_by_key(|(_, cell)| **cell);
+    for (hash, cell) in cells {
+        bytes.extend_from_slice(&hash.to_le_bytes());
+        write_u32(bytes, *cell);
+    }
+    write_u32(bytes, layout.spans.len() as u32);
+    let mut spans = layout.spans.iter().collect::<Vec<_>>();
+    spans.sort_by_key(|(hash, _)| (layout.cells.get(hash), **hash));
+    for (hash, (columns, rows)) in spans {
+        bytes.extend_from_slice(&hash.to_le_bytes());
+        write_u32(bytes, *columns);
+        write_u32(bytes, *rows);
+    }
+}
+
+fn read_layout(reader: &mut Reader) -> Result<Layout> {
+    let group = match reader.take(1)?[0] {
+        0 => None,
+        1 => {
+            let len = reader.u32()? as usize;
+            let group = String::from_utf8(reader.take(len)?.to_vec());
+            Some(group.map_err(|_| Error::Spec("Library data is corrupted."))?)
+        }
+        _ => return Err(Error::Spec("Library data is corrupted.")),
+    };
+    let width = reader.u32()?;
+    let height = reader.u32()?;
+    let columns = reader.u32()?;
+    let rows = reader.u32()?;
+    let count = reader.u32()?;
+    let mut cells = HashMap::new();
+    for _ in 0..count {
+        let hash = reader.u64()?;
+        cells.insert(hash, reader.u32()?);
+    }
+    let count = reader.u32()?;
+    let mut spans = HashMap::new();
+    for _ in 0..count {
+        let hash = reader.u64()?;
+        spans.insert(hash, (reader.u32()?, reader.u32()?));
+    }
+    Ok(Layout {
+        group,
+        width,
+        height,
+        columns,
+        rows,
+        cells,
+        spans,
+    })
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::*;
+    use crate::models::*;
+
+    #[test]
+    fn library_is_restored_from_bytes() {
+        let library = library(vec![&RGBY, &(&C1X1, "a")]);
+        let restored = Library::from_bytes(&library.to_bytes()).unwrap();
+        assert_eq!(restored, library);
+    }
+
+    #[test]
+    fn library_with_spanning_units_is_restored_from_bytes() {
+        let prefs = Prefs {
+            unit_size: 1,
+            padding: 0,
+            reuse_threshold: 0.5,
+            ..Prefs::default()
+        };
+        let arts = crate::dice(&[PLT3X3.sprite(), RGB4X4.sprite()], &prefs).unwrap();
+        let library = Library::new(&arts, &prefs).unwrap();
+        assert_eq!(library.layouts[0].spans.len(), 1);
+        assert_eq!(Library::from_bytes(&library.to_bytes()).unwrap(), library);
+    }
+
+    #[test]
+    fn serialized_library_is_deterministic() {
+        let bytes = library(vec![&RGB4X4, &PLT4X4]).to_bytes();
+        assert_eq!(Library::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+    }
+
+    #[test]
+    #[should_panic(expected = "Library data is not recognized.")]
+    fn errs_when_data_is_not_library() {
+        Library::from_bytes(b"PNG\0\0\0").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Library data is corrupted.")]
+    fn errs_when_data_is_truncated() {
+        let bytes = library(vec![&RGBY]).to_bytes();
+        Library::from_bytes(&bytes[..bytes.len() - 1]).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Library doesn't match unit size, padding and UV inset.")]
+    fn errs_when_dicing_with_library_of_other_unit_size() {
+        let prefs = Prefs {
+            unit_size: 2,
+            ..Prefs::default()
+        };
+        crate::dice_with_library(&library(vec![&RGBY]), &[B1X1.sprite()], &prefs).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Library doesn't match unit size, padding and UV inset.")]
+    fn errs_when_dicing_with_library_of_other_uv_inset() {
+        let prefs = Prefs {
+            unit_size: 1,
+            padding: 0,
+            uv_inset: 0.2,
+            ..Prefs::default()
+        };
+        crate::dice_with_library(&library(vec![&RGBY]), &[B1X1.sprite()], &prefs).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Library data is corrupted.")]
+    fn errs_when_cells_are_out_of_grid() {
+        let mut library = library(vec![&RGBY]);
+        library.layouts[0].cells.insert(0, 4);
+        Library::from_bytes(&library.to_bytes()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Library data is corrupted.")]
+    fn errs_when_grid_overflows() {
+        let mut library = library(vec![&RGBY]);
+        library.layouts[0].columns = u32::MAX;
+        Library::from_bytes(&library.to_bytes()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Layout has no cell grid; atlases packed whole can't be reused.")]
+    fn errs_when_creating_library_of_packed_whole() {
+        let prefs = Prefs {
+            unit_size: 1,
+            padding: 0,
+            ..Prefs::default()
+        };
+        let arts = crate::pack(&[RGBY.sprite()], &prefs).unwrap();
+        Library::new(&arts, &prefs).unwrap();
+    }
+
+    #[test]
+    fn units_in_library_are_not_packed() {
+        let prefs = Prefs {
+            unit_size: 1,
+            padding: 0,
+            ..Prefs::default()
+        };
+        let library = library(vec![&RGBY]);
+        let sprites = [RGBY.sprite(), C1X1.sprite()];
+        let arts = crate::dice_with_library(&library, &sprites, &prefs).unwrap();
+        assert_eq!(arts.layouts.len(), 1);
+        assert_eq!(arts.layouts[0].cells.len(), 1);
+        assert_eq!(arts.sprites[0].atlas_index, 0);
+        assert_eq!(arts.sprites[1].atlas_index, 1);
+    }
+
+    fn library(src: Vec<&dyn AnySource>) -> Library {
+        let prefs = Prefs {
+            unit_size: 1,
+            padding: 0,
+            ..Prefs::default()
+        };
+        let sprites = src.into_iter().map(|s| s.sprite()).collect::<Vec<_>>();
+        Library::new(&crate::dice(&sprites, &prefs).unwrap(), &prefs).unwrap()
+    }
+}
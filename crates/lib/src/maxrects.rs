@@ -0,0 +1,431 @@
+use crate::models::*;
+use crate::packer::{split_groups, uniform};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// Packs whole (non-diced) textures into atlases with the MaxRects algorithm, placing each
+/// unique texture at the free rect it fits best by the short side.
+pub(crate) fn pack(diced: Vec<DicedTexture>, prefs: &Prefs) -> Result<Vec<Atlas>> {
+    if prefs.atlas_size_limit == 0 {
+        return Err(Error::Spec("Atlas size limit can't be zero."));
+    }
+
+    let mut atlases = vec![];
+    for group in split_groups(diced) {
+        pack_group(group, prefs, &mut atlases)?;
+    }
+    if prefs.atlas_uniform {
+        uniform(&mut atlases, prefs);
+    }
+
+    Ok(atlases)
+}
+
+/// Evaluates layout of an atlas packed with [pack]; as the atlas is not divided into cells,
+/// the layout only describes the atlas dimensions.
+pub(crate) fn layout(atlas: &Atlas) -> Layout {
+    Layout {
+        group: atlas.group.to_owned(),
+        width: atlas.texture.width,
+        height: atlas.texture.height,
+        columns: 0,
+        rows: 0,
+        cells: HashMap::new(),
+        spans: HashMap::new(),
+    }
+}
+
+struct Bin {
+    /// Maximal rects not occupied by the placed units; may overlap each other.
+    free: Vec<URect>,
+    /// Rects occupied by the placed units (padding included), mapped by unit hashes.
+    used: HashMap<u64, URect>,
+}
+
+fn pack_group(group: Vec<DicedTexture>, prefs: &Prefs, atlases: &mut Vec<Atlas>) -> Result<()> {
+    // With POT enforced, bins are limited to the largest POT fitting the limit, so that
+    // the atlases could be rounded up to POT without exceeding the limit.
+    let side = match prefs.atlas_pot {
+        true => 1 << (31 - prefs.atlas_size_limit.leading_zeros()),
+        false => prefs.atlas_size_limit,
+    };
+
+    // Identical textures share the placement, hence only the first occurrences are packed.
+    let mut seen = HashSet::new();
+    let mut units: Vec<&DicedUnit> = group
+        .iter()
+        .flat_map(|t| t.units.iter())
+        .filter(|u| seen.insert(u.hash))
+        .collect();
+    units.sort_by_key(|u| {
+        let size = &u.size;
+        (
+            Reverse(size.width.max(size.height)),
+            Reverse(size.width * size.height),
+        )
+    });
+
+    let mut bins: Vec<Bin> = vec![];
+    for (idx, unit) in units.iter().enumerate() {
+        Progress::report(prefs, 2, idx, units.len(), "Packing sprites");
+        if unit.size.width > side || unit.size.height > side {
+            return Err(Error::Spec(
+                "Can't fit single texture; increase atlas size limit.",
+            ));
+        }
+        if !bins.iter_mut().any(|b| place(b, unit)) {
+            let free = vec![URect::new(0, 0, side, side)];
+            let mut bin = Bin {
+                free,
+                used: HashMap::new(),
+            };
+            place(&mut bin, unit);
+            bins.push(bin);
+        }
+    }
+
+    let units: HashMap<u64, &DicedUnit> = units.into_iter().map(|u| (u.hash, u)).collect();
+    let baked: Vec<_> = bins.iter().map(|b| bake_atlas(b, &units, prefs)).collect();
+    let mut packed: Vec<Vec<DicedTexture>> = bins.iter().map(|_| vec![]).collect();
+    let group_id = group[0].group.to_owned();
+    for texture in group {
+        let hash = texture.units[0].hash;
+        let idx = bins
+            .iter()
+            .position(|b| b.used.contains_key(&hash))
+            .unwrap();
+        packed[idx].push(texture);
+    }
+    for ((texture, rects), packed) in baked.into_iter().zip(packed) {
+        atlases.push(Atlas {
+            texture,
+            rects,
+            cells: HashMap::new(),
+            spans: HashMap::new(),
+            group: group_id.to_owned(),
+            packed,
+        });
+    }
+
+    Ok(())
+}
+
+/// Places specified unit into the best fitting free rect of the bin; returns false when
+/// there is no free rect to fit the unit.
+fn place(bin: &mut Bin, unit: &DicedUnit) -> bool {
+    let (width, height) = (unit.size.width, unit.size.height);
+    let best = bin
+        .free
+        .iter()
+        .filter(|f| f.width >= width && f.height >= height)
+        .min_by_key(|f| {
+            let (dw, dh) = (f.width - width, f.height - height);
+            (dw.min(dh), dw.max(dh), f.y, f.x)
+        });
+    let Some(free) = best else {
+        return false;
+    };
+    let rect = URect::new(free.x, free.y, width, height);
+    split_free_rects(bin, &rect);
+    bin.used.insert(unit.hash, rect);
+    true
+}
+
+/// Replaces free rects overlapped by the specified used rect with the maximal rects
+/// of their remaining area, then drops the free rects contained in the others.
+fn split_free_rects(bin: &mut Bin, used: &URect) {
+    let mut split = vec![];
+    for free in bin.free.drain(..) {
+        if !overlaps(&free, used) {
+            split.push(free);
+            continue;
+        }
+        if used.x > free.x {
+            split.push(URect::new(free.x, free.y, used.x - free.x, free.height));
+        }
+        if right(used) < right(&free) {
+            let width = right(&free) - right(used);
+            split.push(URect::new(right(used), free.y, width, free.height));
+        }
+        if used.y > free.y {
+            split.push(URect::new(free.x, free.y, free.width, used.y - free.y));
+        }
+        if bottom(used) < bottom(&free) {
+            let height = bottom(&free) - bottom(used);
+            split.push(URect::new(free.x, bottom(used), free.width, height));
+        }
+    }
+    for (idx, rect) in split.iter().enumerate() {
+        let redundant = split.iter().enumerate().any(|(other_idx, other)| {
+            other_idx != idx && contains(other, rect) && (other != rect || other_idx < idx)
+        });
+        if !redundant {
+            bin.free.push(rect.to_owned());
+        }
+    }
+}
+
+fn bake_atlas(
+    bin: &Bin,
+    units: &HashMap<u64, &DicedUnit>,
+    prefs: &Prefs,
+) -> (Texture, HashMap<u64, FRect>) {
+    let size = eval_atlas_size(bin, prefs);
+    let mut rects = HashMap::new();
+    let mut texture = Texture {
+        width: size.width,
+        height: size.height,
+        pixels: vec![Pixel::default(); (size.width * size.height) as usize],
+    };
+
+    for (hash, rect) in bin.used.iter() {
+        let unit = units[hash];
+        for (y, row) in unit.pixels.chunks(rect.width as usize).enumerate() {
+            let start = (rect.y as usize + y) * size.width as usize + rect.x as usize;
+            texture.pixels[start..start + row.len()].copy_from_slice(row);
+        }
+        let x = (rect.x + prefs.padding) as f32 / size.width as f32;
+        let y = (rect.y + prefs.padding) as f32 / size.height as f32;
+        let width = unit.rect.width as f32 / size.width as f32;
+        let height = unit.rect.height as f32 / size.height as f32;
+        rects.insert(*hash, FRect::new(x, y, width, height));
+    }
+
+    (texture, rects)
+}
+
+fn eval_atlas_size(bin: &Bin, prefs: &Prefs) -> USize {
+    let mut width = bin.used.values().map(right).max().unwrap_or(0);
+    let mut height = bin.used.values().map(bottom).max().unwrap_or(0);
+    if prefs.atlas_pot {
+        width = width.next_power_of_two();
+        height = height.next_power_of_two();
+    }
+    if prefs.atlas_square {
+        width = width.max(height);
+        height = width;
+    }
+    USize::new(width, height)
+}
+
+fn right(rect: &URect) -> u32 {
+    rect.x + rect.width
+}
+
+fn bottom(rect: &URect) -> u32 {
+    rect.y + rect.height
+}
+
+fn overlaps(a: &URect, b: &URect) -> bool {
+    a.x < right(b) && b.x < right(a) && a.y < bottom(b) && b.y < bottom(a)
+}
+
+fn contains(outer: &URect, inner: &URect) -> bool {
+    outer.x <= inner.x
+        && outer.y <= inner.y
+        && right(inner) <= right(outer)
+        && bottom(inner) <= bottom(outer)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures::*;
+    use crate::models::*;
+
+    #[test]
+    fn can_pack_with_defaults() {
+        assert!(try_pack(vec![&RGB4X4, &BGRT], &Prefs::default()).is_ok());
+    }
+
+    #[test]
+    fn errs_when_limit_is_zero() {
+        let prefs = Prefs {
+            atlas_size_limit: 0,
+            ..defaults()
+        };
+        assert!(try_pack(vec![&R1X1], &prefs).is_err());
+    }
+
+    #[test]
+    fn errs_on_invalid_prefs() {
+        let prefs = Prefs {
+            lod_count: 17,
+            ..defaults()
+        };
+        assert!(try_pack(vec![&R1X1], &prefs).is_err());
+    }
+
+    #[test]
+    fn lods_are_not_built() {
+        let prefs = Prefs {
+            lod_count: 2,
+            ppu: 1.0,
+            ..defaults()
+        };
+        let packed = crate::pack(&[RGB4X4.sprite()], &prefs).unwrap();
+        assert!(packed.sprites[0].lods.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Layout has no cell grid; atlases packed whole can't be reused.")]
+    fn errs_when_appending_to_packed_whole() {
+        let prefs = Prefs {
+            unit_size: 1,
+            ..defaults()
+        };
+        let previous = crate::pack(&[RGB4X4.sprite()], &prefs).unwrap();
+        crate::append(&previous, &[R1X1.sprite()], &prefs).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Layout has no cell grid; atlases packed whole can't be reused.")]
+    fn errs_when_patching_packed_whole() {
+        let prefs = Prefs {
+            unit_size: 1,
+            ..defaults()
+        };
+        let shipped = crate::pack(&[RGB4X4.sprite()], &prefs).unwrap();
+        crate::patch(&shipped.layouts, &[R1X1.sprite()], &prefs).unwrap();
+    }
+
+    #[test]
+    fn errs_when_texture_doesnt_fit() {
+        let prefs = Prefs {
+            atlas_size_limit: 3,
+            ..defaults()
+        };
+        assert!(try_pack(vec![&RGB4X4], &prefs).is_err());
+    }
+
+    #[test]
+    fn atlas_is_trimmed_to_content() {
+        let atlases = pack(vec![&RGB4X4, &R1X1], &defaults());
+        assert_eq!(atlases.len(), 1);
+        assert_eq!(atlases[0].texture.width * atlases[0].texture.height, 20);
+    }
+
+    #[test]
+    fn identical_textures_share_area() {
+        let atlases = pack(vec![&RGB4X4, &RGB4X4], &defaults());
+        assert_eq!(atlases[0].rects.len(), 1);
+        assert_eq!(atlases[0].packed.len(), 2);
+        assert_eq!(atlases[0].texture.width * atlases[0].texture.height, 16);
+    }
+
+    #[test]
+    fn when_content_doesnt_fit_multiple_atlases_are_produced() {
+        let prefs = Prefs {
+            atlas_size_limit: 4,
+            ..defaults()
+        };
+        let atlases = pack(vec![&RGB4X4, &BGRT, &R1X1], &prefs);
+        assert_eq!(atlases.len(), 2);
+    }
+
+    #[test]
+    fn when_pot_forced_atlas_is_power_of_two() {
+        let prefs = Prefs {
+            atlas_pot: true,
+            ..defaults()
+        };
+        let atlas = pack(vec![&RGB4X4, &R1X1], &prefs).pop().unwrap();
+        assert!(atlas.texture.width.is_power_of_two());
+        assert!(atlas.texture.height.is_power_of_two());
+    }
+
+    #[test]
+    fn when_pot_forced_atlas_doesnt_exceed_limit() {
+        let prefs = Prefs {
+            atlas_size_limit: 6,
+            atlas_pot: true,
+            ..defaults()
+        };
+        let atlases = pack(vec![&RGB4X4, &R1X1], &prefs);
+        assert!(atlases.iter().all(|a| a.texture.width <= 4));
+        assert!(atlases.iter().all(|a| a.texture.height <= 4));
+    }
+
+    #[test]
+    fn when_square_forced_atlas_is_square() {
+        let prefs = Prefs {
+            atlas_square: true,
+            ..defaults()
+        };
+        let atlas = pack(vec![&RGB4X4, &R1X1], &prefs).pop().unwrap();
+        assert_eq!(atlas.texture.width, atlas.texture.height);
+    }
+
+    #[test]
+    fn padded_rects_dont_overlap() {
+        let prefs = Prefs {
+            padding: 1,
+            ..defaults()
+        };
+        let src: Vec<&dyn AnySource> = vec![
+            &RGB4X4, &BGRT, &R1X1, &CMYT, &RGB1X3, &RGB3X1, &PLT4X4, &PLT3X3,
+        ];
+        let len = src.len();
+        let atlases = pack(src, &prefs);
+        let rects: Vec<_> = atlases[0]
+            .rects
+            .values()
+            .map(|r| to_px(r, &atlases[0]))
+            .collect();
+        assert_eq!(rects.len(), len);
+        for (idx, a) in rects.iter().enumerate() {
+            for b in rects.iter().skip(idx + 1) {
+                let (ax, ay, aw, ah) = (a.x - 1, a.y - 1, a.width + 2, a.height + 2);
+                let (bx, by, bw, bh) = (b.x - 1, b.y - 1, b.width + 2, b.height + 2);
+                assert!(ax + aw <= bx || bx + bw <= ax || ay + ah <= by || by + bh <= ay);
+            }
+        }
+    }
+
+    #[test]
+    fn uvs_are_mapped_to_content() {
+        let prefs = Prefs {
+            padding: 1,
+            ..defaults()
+        };
+        let atlas = pack(vec![&RGB4X4], &prefs).pop().unwrap();
+        let rect = atlas.rects.values().next().unwrap();
+        assert_eq!(
+            *rect,
+            FRect::new(1.0 / 6.0, 1.0 / 6.0, 4.0 / 6.0, 4.0 / 6.0)
+        );
+    }
+
+    #[test]
+    fn groups_are_packed_separately() {
+        let atlases = pack(vec![&(&R1X1, "R"), &(&B1X1, "B")], &defaults());
+        assert_eq!(atlases.len(), 2);
+    }
+
+    fn to_px(rect: &FRect, atlas: &Atlas) -> URect {
+        let (width, height) = (atlas.texture.width as f32, atlas.texture.height as f32);
+        URect::new(
+            (rect.x * width).round() as u32,
+            (rect.y * height).round() as u32,
+            (rect.width * width).round() as u32,
+            (rect.height * height).round() as u32,
+        )
+    }
+
+    fn pack(src: Vec<&dyn AnySource>, prefs: &Prefs) -> Vec<Atlas> {
+        try_pack(src, prefs).unwrap()
+    }
+
+    fn try_pack(src: Vec<&dyn AnySource>, prefs: &Prefs) -> Result<Vec<Atlas>> {
+        let sprites = src.into_iter().map(|s| s.sprite()).collect::<Vec<_>>();
+        let diced = crate::dicer::dice_whole(&sprites, prefs)?;
+        crate::maxrects::pack(diced, prefs)
+    }
+
+    fn defaults() -> Prefs {
+        Prefs {
+            atlas_size_limit: 16,
+            padding: 0,
+            ..Prefs::default()
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Common data models.
 
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Result of a dicing operation.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -10,12 +11,19 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     /// An issue with [Prefs] and/or input data.
     Spec(&'static str),
+    /// Sprite with the specified ID can't be built within [Prefs::vertex_budget].
+    Budget(String),
+    /// Vertices of the sprite with the specified ID exceed the 16-bit range of
+    /// [DicedSprite::quantized].
+    Quantize(String),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Spec(info) => write!(f, "{info}"),
+            Error::Budget(id) => write!(f, "Sprite '{id}' exceeds the vertex budget."),
+            Error::Quantize(id) => write!(f, "Sprite '{id}' exceeds the quantization range."),
         }
     }
 }
@@ -38,6 +46,11 @@ pub struct Prefs {
     /// Whether to trim transparent areas on the built meshes.
     /// Disable to preserve aspect ratio of the source sprites (usable for animations).
     pub trim_transparent: bool,
+    /// Sprites with reuse ratio (share of the sprite's units, which content is repeated in
+    /// other units) below the threshold are packed whole, as a single trimmed rect, instead of
+    /// being diced, avoiding mesh and padding overhead when dicing doesn't save texture space.
+    /// Zero (default) disables the fallback.
+    pub reuse_threshold: f32,
     /// Maximum size (width or height) of a single generated atlas texture; will generate
     /// multiple textures when the limit is reached.
     pub atlas_size_limit: u32,
@@ -47,11 +60,70 @@ pub struct Prefs {
     /// The generated atlas textures will always have width and height be power of two.
     /// Extremely inefficient, but required by some older GPUs.
     pub atlas_pot: bool,
+    /// All the atlas textures generated for a group will have identical dimensions, allowing
+    /// to bind them as layers of a single texture array. Consumes more texture space.
+    pub atlas_uniform: bool,
+    /// Units will be distributed evenly over the minimum number of atlas textures required to
+    /// fit them, instead of filling each atlas up to the size limit. Produces atlases of similar
+    /// size, avoiding a nearly empty last atlas.
+    pub atlas_balance: bool,
     /// Pixel per unit ratio to use when evaluating positions of the generated mesh vertices.
     /// Higher values will make sprite smaller in conventional space units.
     pub ppu: f32,
-    /// Origin of the generated mesh, in relative offsets from top-left corner of the sprite rect.
+    /// Origin of the generated mesh, in relative offsets from top-left corner of the sprite rect
+    /// (bottom-left when [y_up] is enabled).
     pub pivot: Pivot,
+    /// Origin of the generated mesh fixed in the source texture space; when specified, takes
+    /// priority over [pivot] for the sprites without own pivot or origin.
+    pub origin: Option<Origin>,
+    /// Whether Y axis of the vertex positions points up, instead of down. The sprite rects and
+    /// pivots are then relative to the bottom-left corner, instead of the top-left.
+    pub y_up: bool,
+    /// Whether origin of the atlas texture coordinates is at bottom-left, instead of top-left.
+    pub uv_bottom_left: bool,
+    /// Whether the mesh faces are wound clockwise, instead of counter-clockwise, as seen when
+    /// rendered with the configured Y axis direction.
+    pub clockwise: bool,
+    /// Whether to build the sprite meshes as quads: [DicedSprite::vertices], [DicedSprite::uvs]
+    /// and [DicedSprite::indices]. Disable when only the instances are required.
+    pub build_quads: bool,
+    /// Whether to merge the mesh vertices with identical positions and UVs, such as shared
+    /// corners of the adjacent units sampling adjacent atlas cells.
+    pub weld_vertices: bool,
+    /// Whether to build [DicedSprite::quantized] compact integer alternative of the vertices
+    /// and UVs. Fails with [Error::Quantize] when a sprite exceeds the 16-bit range.
+    pub quantize: bool,
+    /// Whether to build per-unit [DicedSprite::instances] for GPU-instanced rendering.
+    pub build_instances: bool,
+    /// Whether instances sampling identical UV rects should share the rects in
+    /// [DicedSprite::instance_uvs], instead of each instance having a dedicated rect.
+    pub merge_instance_uvs: bool,
+    /// Whether to build [DicedSprite::indirection] page table texture for rendering the
+    /// sprites as single quads, resolving the units in a fragment shader.
+    pub build_indirection: bool,
+    /// Whether to build [DicedSprite::outline] polygon of the opaque region, e.g. for physics
+    /// and hit-testing. Traced over the source textures, so not affected by dicing.
+    pub build_outline: bool,
+    /// Minimum alpha of a source texture pixel to be enclosed by the outline.
+    pub outline_threshold: u8,
+    /// Maximum distance, in source texture pixels, the simplified outline is allowed to deviate
+    /// from the traced pixel borders. Zero preserves the borders exactly.
+    pub outline_tolerance: f32,
+    /// Number of additional lower-detail meshes to build per sprite, see [DicedSprite::lods].
+    /// Each level is diced from the source texture downscaled twice the previous level, so that
+    /// a unit covers 4 times the area with a quarter of the quads. Consumes more atlas space.
+    pub lod_count: u32,
+    /// Maximum number of mesh vertices per sprite; zero (default) disables the limit. Sprites
+    /// which built meshes exceed the budget are re-diced with units 2, 4, etc times larger
+    /// (spanning multiple atlas cells) until the mesh fits, or packed whole as the last resort;
+    /// when even the single quad doesn't fit (eg, due to nine-slice splits), the operation
+    /// fails with [Error::Budget]. Requires [Prefs::build_quads]; [DicedSprite::lods] are
+    /// not limited by the budget.
+    pub vertex_budget: u32,
+    /// Whether to detect source sprites with identical pixels and settings (eg, duplicated
+    /// animation frames) and emit the duplicates as [Artifacts::aliases] referencing the
+    /// first sprite, instead of building identical meshes.
+    pub alias_duplicates: bool,
     /// Callback to invoke when dicing operation progress changes in a meaningful way.
     pub on_progress: Option<ProgressCallback>,
 }
@@ -63,11 +135,30 @@ impl Default for Prefs {
             padding: 2,
             uv_inset: 0.0,
             trim_transparent: true,
+            reuse_threshold: 0.0,
             atlas_size_limit: 2048,
             atlas_square: false,
             atlas_pot: false,
+            atlas_uniform: false,
+            atlas_balance: false,
             ppu: 100.0,
             pivot: Pivot { x: 0.5, y: 0.5 },
+            origin: None,
+            y_up: false,
+            uv_bottom_left: false,
+            clockwise: false,
+            build_quads: true,
+            weld_vertices: false,
+            quantize: false,
+            build_instances: false,
+            merge_instance_uvs: false,
+            build_indirection: false,
+            build_outline: false,
+            outline_threshold: 128,
+            outline_tolerance: 1.0,
+            lod_count: 0,
+            vertex_budget: 0,
+            alias_duplicates: false,
             on_progress: None,
         }
     }
@@ -88,7 +179,7 @@ pub struct Progress {
 impl Progress {
     pub fn report(prefs: &Prefs, stage: u8, idx: usize, len: usize, activity: &str) {
         // Stages:
-        // 0 Decoding source textures (cli only)
+        // 0 Decoding source textures (cli only; done while dicing when streamed)
         // 1 Dicing source textures
         // 2 Packing diced units
         // 3 Building diced sprites
@@ -153,6 +244,19 @@ pub struct SourceSprite {
     /// Relative position of the sprite origin point on the generated mesh.
     /// When not specified, will use default pivot specified in [Prefs].
     pub pivot: Option<Pivot>,
+    /// Origin of the sprite mesh fixed in the source texture space. Takes priority over
+    /// [pivot] when specified.
+    pub origin: Option<Origin>,
+    /// Key of the group the sprite belongs to. Sprites of distinct groups are never packed
+    /// into the same atlas, so that each group produces its own atlas series. When not
+    /// specified, the sprite is packed with other sprites that have no group.
+    pub group: Option<String>,
+    /// Pixels per unit to scale the sprite's mesh with. When not specified, will use default
+    /// PPU specified in [Prefs].
+    pub ppu: Option<f32>,
+    /// Nine-slice borders of the sprite. When specified, the mesh quads are split at the slice
+    /// lines, so that runtimes can stretch the center and edges; see [DicedSprite::slices].
+    pub border: Option<Border>,
 }
 
 /// Final products of a dicing operation.
@@ -160,8 +264,87 @@ pub struct SourceSprite {
 pub struct Artifacts {
     /// Generated atlas textures containing unique pixel content of the diced sprites.
     pub atlases: Vec<Texture>,
+    /// Placement of the diced units inside the [atlases], in the same order.
+    pub layouts: Vec<Layout>,
     /// Generated diced sprites with data to reconstruct source spites: mesh, uvs, etc.
     pub sprites: Vec<DicedSprite>,
+    /// Source sprites identical to some of the [sprites], which are not built separately;
+    /// empty unless [Prefs::alias_duplicates] is enabled.
+    pub aliases: Vec<Alias>,
+}
+
+/// Source sprite identical to another one, which is to be rendered with the other's mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alias {
+    /// Identifier of the duplicate source sprite.
+    pub id: String,
+    /// Identifier of the diced sprite with the mesh to render the duplicate with.
+    pub target: String,
+}
+
+/// Products of appending sprites to previously generated [Artifacts].
+#[derive(Debug, Clone)]
+pub struct Appended {
+    /// All the atlases with their layouts, including unchanged ones, and diced sprites
+    /// generated for the appended source sprites.
+    pub artifacts: Artifacts,
+    /// Indexes of the atlases that were modified or added while appending.
+    pub changed: Vec<usize>,
+}
+
+/// Placement of diced units inside an atlas texture, which is a grid of equally-sized cells,
+/// each containing a single unit with padding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    /// Group of the sprites packed into the atlas, if any.
+    pub group: Option<String>,
+    /// Width of the atlas texture, in pixels.
+    pub width: u32,
+    /// Height of the atlas texture, in pixels.
+    pub height: u32,
+    /// Number of cells over horizontal axis of the atlas.
+    pub columns: u32,
+    /// Number of cells over vertical axis of the atlas.
+    pub rows: u32,
+    /// Indexes of the cells occupied by the packed units, mapped by unit content hashes.
+    /// Cells are counted left to right, top to bottom.
+    pub cells: HashMap<u64, u32>,
+    /// Number of columns and rows occupied by the units spanning multiple cells (sprites
+    /// packed whole), mapped by unit content hashes; other units occupy a single cell
+    /// under the index from [cells], which is top-left for the spanning units.
+    pub spans: HashMap<u64, (u32, u32)>,
+}
+
+impl Layout {
+    /// Ratio of the cells occupied by the packed units to all the atlas cells, in 0.0 to 1.0 range.
+    /// Spanning units count all the cells they cover.
+    pub fn occupancy(&self) -> f32 {
+        let area = |hash| {
+            self.spans
+                .get(hash)
+                .map_or(1, |(c, r)| *c as u64 * *r as u64)
+        };
+        let occupied = self.cells.keys().map(area).sum::<u64>();
+        match self.columns as u64 * self.rows as u64 {
+            0 => 0.0,
+            total => occupied as f32 / total as f32,
+        }
+    }
+}
+
+/// Diced units of a base sprite set (eg, a shared UI kit), which can be referenced by other
+/// dicing operations instead of packing the units again; see [crate::dice_with_library].
+/// Can be serialized with [Library::to_bytes] and restored with [Library::from_bytes].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Library {
+    /// The size of the diced units, in pixels.
+    pub unit_size: u32,
+    /// The size of border, in pixels, between adjacent units inside the library atlases.
+    pub padding: u32,
+    /// Relative inset of the UVs the library units are sampled with.
+    pub uv_inset: f32,
+    /// Placement of the diced units inside the library atlases.
+    pub layouts: Vec<Layout>,
 }
 
 /// Generated dicing product of a [SourceSprite] containing mesh data and reference to the
@@ -171,17 +354,188 @@ pub struct DicedSprite {
     /// ID of the source sprite based on which this sprite is generated.
     pub id: String,
     /// Index of atlas texture in [Artifacts] containing the unique pixels for this sprite.
+    /// When the sprite is produced with [crate::patch], indexes below the number of shipped
+    /// layouts refer to the shipped atlases, while the rest refer to the [Artifacts] atlases,
+    /// offset by the number of shipped layouts.
     pub atlas_index: usize,
+    /// Indexes of atlas textures sampled by each mesh face (triangle) formed by [indices].
+    /// Empty when all the faces sample the atlas under [atlas_index], which is always the case,
+    /// unless the sprite is produced with [crate::patch] and references both shipped and new
+    /// units; index semantics are the same as with [atlas_index].
+    pub atlas_indices: Vec<usize>,
+    /// Index of the atlas texture among the atlases generated for the sprite's group. When
+    /// [Prefs::atlas_uniform] is enabled, can be used as layer index of a texture array.
+    pub layer: usize,
     /// Local position of the generated sprite mesh vertices.
     pub vertices: Vec<Vertex>,
     /// Atlas texture coordinates mapped to the [vertices] vector.
     pub uvs: Vec<Uv>,
     /// Mesh face (triangle) indices to the [vertices] and [uvs] vectors.
-    pub indices: Vec<usize>,
+    pub indices: Indices,
     /// Rect of the sprite in conventional units space, aka boundaries.
     pub rect: Rect,
     /// Relative position of the sprite origin point on the generated mesh.
     pub pivot: Pivot,
+    /// Key of the group the source sprite belongs to, if any.
+    pub group: Option<String>,
+    /// Whether the source sprite is packed whole, due to reuse ratio being below
+    /// [Prefs::reuse_threshold] or the diced mesh exceeding [Prefs::vertex_budget] even with
+    /// the larger units; the mesh is then a single quad covering trimmed sprite rect.
+    pub whole: bool,
+    /// Page table mapping the sprite units to the atlas cells, allowing to render the sprite
+    /// as a single quad, resolving the units in a fragment shader. Each texel corresponds to
+    /// a unit of the source texture, so the dimensions are the source texture dimensions
+    /// divided by [Prefs::unit_size], rounded up; texels are in the same order as pixels of
+    /// the source texture. Occupied cell column and row on the atlas are encoded as 12-bit
+    /// numbers: R holds low 8 bits of the column, G — low 8 bits of the row, B — high 4 bits
+    /// of the column in low nibble and high 4 bits of the row in high nibble. Alpha is 255
+    /// for units present in the atlas and 0 for the transparent units, which are skipped.
+    /// Atlas pixel of a unit cell is at `cell * (unit_size + padding * 2) + padding`.
+    /// Built when [Prefs::build_indirection] is enabled; None otherwise, as well as when
+    /// the sprite is packed [whole] or with larger units to fit [Prefs::vertex_budget], samples
+    /// multiple atlases ([atlas_indices] is not empty) or the atlas has more than 4096 columns
+    /// or rows.
+    pub indirection: Option<Texture>,
+    /// Compact alternative to the mesh data, mapping each unit of the source texture to
+    /// the atlas cell containing its pixels, allowing runtimes to build the meshes on their
+    /// own or render the sprite with a tilemap. None when the sprite is packed [whole] or with
+    /// larger units to fit [Prefs::vertex_budget], samples multiple atlases or the atlas has
+    /// more than 4096 columns or rows.
+    pub grid: Option<Grid>,
+    /// Per-unit instance records, when [Prefs::build_instances] is enabled; in the same order
+    /// as the mesh quads, so that, when [atlas_indices] is not empty, the atlas of an instance
+    /// is under twice its index.
+    pub instances: Vec<Instance>,
+    /// Atlas texture UV rects sampled by the [instances].
+    pub instance_uvs: Vec<Rect>,
+    /// Integer alternative of the [vertices] and [uvs], when [Prefs::quantize] is enabled.
+    pub quantized: Option<Quantized>,
+    /// Closed paths enclosing opaque region of the sprite, when [Prefs::build_outline] is
+    /// enabled; in the same space as the [vertices]. Outer paths are wound as the mesh faces,
+    /// while the paths of holes are wound in opposite direction.
+    pub outline: Vec<Vec<Vertex>>,
+    /// Nine-slice borders of the associated [SourceSprite], if any.
+    pub border: Option<Border>,
+    /// When [border] is specified, index of the nine-slice region each vertex belongs to,
+    /// from 0 (top-left) to 8 (bottom-right) in row-major order, as seen on the source texture;
+    /// empty otherwise. The regions are not shared: quads crossing the slice lines are split.
+    pub slices: Vec<u8>,
+    /// Lower-detail variants of the mesh, when [Prefs::lod_count] is above zero; first is the
+    /// most detailed. The meshes are in the same space as the [vertices] and are clipped to
+    /// the [rect]. Empty when the sprite is packed [whole] or the quads are not built.
+    pub lods: Vec<Lod>,
+}
+
+/// Lower-detail variant of a diced sprite mesh, sampling units diced from downscaled source
+/// texture; see [Prefs::lod_count].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lod {
+    /// Indexes of the atlas textures sampled by each face, in case some of the faces sample
+    /// other than [DicedSprite::atlas_index] atlas; empty otherwise.
+    pub atlas_indices: Vec<usize>,
+    /// Mesh vertex positions, same as [DicedSprite::vertices].
+    pub vertices: Vec<Vertex>,
+    /// Atlas texture coordinates mapped to the [vertices].
+    pub uvs: Vec<Uv>,
+    /// Mesh face indices to the [vertices].
+    pub indices: Indices,
+}
+
+/// Mesh vertices and UVs of a diced sprite quantized to 16-bit integers. As the units are
+/// aligned to the pixel grid of the source texture, the vertices are exact. The UVs are
+/// generally not (eg, a pixel of 256 wide atlas is not a multiple of 1/65535), though, unless
+/// [Prefs::uv_inset] is used or atlas dimensions exceed 65535 pixels, the exact atlas pixel
+/// is recovered with `round(u / 65535 * width)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantized {
+    /// Position of the mesh origin (pivot) in the pixel space of the [vertices]; may be
+    /// fractional. Vertex in conventional units is `(vertex - origin) / ppu`.
+    pub origin: Vertex,
+    /// Positions of the [DicedSprite::vertices] in pixels, relative to the top-left corner of
+    /// the source texture (with Y axis direction according to [Prefs::y_up]).
+    pub vertices: Vec<[i16; 2]>,
+    /// [DicedSprite::uvs] normalized to 16-bit integers; UV is `uv / 65535`.
+    pub uvs: Vec<[u16; 2]>,
+}
+
+/// Instance record of a diced unit, to render the unit with GPU instancing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    /// Local rect of the unit quad, in the same space as [DicedSprite::vertices].
+    pub rect: Rect,
+    /// Index of the atlas texture UV rect in [DicedSprite::instance_uvs].
+    pub uv: usize,
+}
+
+/// Grid of the units of a diced sprite source texture mapped to the atlas cells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// Number of the units over horizontal axis: source texture width divided by the unit
+    /// size, rounded up.
+    pub columns: u32,
+    /// Number of the units over vertical axis: source texture height divided by the unit
+    /// size, rounded up.
+    pub rows: u32,
+    /// Indexes of the atlas cells containing the units, row by row starting at the top-left,
+    /// same as pixels of the source texture; None for the transparent units. Atlas cell index
+    /// is `row * columns + column`, where columns is the number of the units fitting the atlas
+    /// width, same as in [Layout::cells].
+    pub cells: Vec<Option<u32>>,
+}
+
+/// Mesh face indices, stored in the smallest type fitting the indexed vertices.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Indices {
+    /// Used when all the indices are below 65536.
+    U16(Vec<u16>),
+    /// Used when some of the indices don't fit 16 bits.
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    /// Number of the indices.
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    /// Whether there are no indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns index under specified position, if any.
+    pub fn get(&self, idx: usize) -> Option<usize> {
+        match self {
+            Indices::U16(indices) => indices.get(idx).map(|i| *i as usize),
+            Indices::U32(indices) => indices.get(idx).map(|i| *i as usize),
+        }
+    }
+
+    /// Iterates the indices widened to [usize].
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).map(|idx| self.get(idx).unwrap())
+    }
+}
+
+impl TryFrom<Vec<usize>> for Indices {
+    type Error = Error;
+
+    /// Stores the indices in the smallest fitting type; fails when some don't fit 32 bits.
+    fn try_from(indices: Vec<usize>) -> Result<Self> {
+        if indices.iter().all(|i| *i <= u16::MAX as usize) {
+            return Ok(Indices::U16(
+                indices.into_iter().map(|i| i as u16).collect(),
+            ));
+        }
+        let indices = indices.into_iter().map(u32::try_from);
+        match indices.collect::<std::result::Result<_, _>>() {
+            Ok(indices) => Ok(Indices::U32(indices)),
+            Err(_) => Err(Error::Spec("Mesh index exceeds 32-bit range.")),
+        }
+    }
 }
 
 /// A rectangle in conventional units space.
@@ -226,6 +580,75 @@ impl Pivot {
     }
 }
 
+/// Distances from the source texture edges to the nine-slice lines, in pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Border {
+    /// Distance from the left edge to the left slice line.
+    pub left: u32,
+    /// Distance from the top edge to the top slice line.
+    pub top: u32,
+    /// Distance from the right edge to the right slice line.
+    pub right: u32,
+    /// Distance from the bottom edge to the bottom slice line.
+    pub bottom: u32,
+}
+
+/// Origin point of the sprite mesh fixed in the source texture space. Unlike [Pivot], which
+/// is relative to the sprite rect, the origin doesn't shift when transparent areas are trimmed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Origin {
+    /// XY position in pixels, counted from top-left corner of the source texture; may be
+    /// fractional and lie outside the texture.
+    Pixel(f32, f32),
+    /// Named point on the source texture borders.
+    Anchor(Anchor),
+}
+
+impl Origin {
+    /// Evaluates XY position of the origin in pixels of a texture with specified dimensions.
+    pub fn to_pixel(&self, width: u32, height: u32) -> (f32, f32) {
+        match self {
+            Origin::Pixel(x, y) => (*x, *y),
+            Origin::Anchor(anchor) => {
+                let (x, y) = anchor.to_relative();
+                (x * width as f32, y * height as f32)
+            }
+        }
+    }
+}
+
+/// Named point on the source texture borders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    MiddleLeft,
+    Center,
+    MiddleRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Evaluates relative (in 0.0-1.0 range) XY position of the anchor, counted from
+    /// top-left corner of the texture.
+    pub fn to_relative(&self) -> (f32, f32) {
+        let x = match self {
+            Anchor::TopLeft | Anchor::MiddleLeft | Anchor::BottomLeft => 0.0,
+            Anchor::TopCenter | Anchor::Center | Anchor::BottomCenter => 0.5,
+            Anchor::TopRight | Anchor::MiddleRight | Anchor::BottomRight => 1.0,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::TopCenter | Anchor::TopRight => 0.0,
+            Anchor::MiddleLeft | Anchor::Center | Anchor::MiddleRight => 0.5,
+            Anchor::BottomLeft | Anchor::BottomCenter | Anchor::BottomRight => 1.0,
+        };
+        (x, y)
+    }
+}
+
 /// Represents position of a mesh vertex in a local space coordinated with conventional units.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Vertex {
@@ -256,6 +679,83 @@ impl Uv {
     }
 }
 
+/// Description of an interleaved vertex buffer to write diced sprite meshes into.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexLayout {
+    /// Attributes of a vertex; positioned by their offsets, hence can be in any order.
+    pub elements: Vec<VertexElement>,
+    /// Distance between starts of adjacent vertices, in bytes; can exceed the total size of
+    /// the elements to leave space for the attributes not provided by the library.
+    pub stride: usize,
+}
+
+/// Vertex attribute placement in a [VertexLayout].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexElement {
+    /// Source data of the element.
+    pub attribute: VertexAttribute,
+    /// Numeric format of the element's two components.
+    pub format: VertexFormat,
+    /// Offset of the element from start of the vertex, in bytes.
+    pub offset: usize,
+}
+
+/// Source data of a [VertexElement].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexAttribute {
+    /// X and Y of [DicedSprite::vertices].
+    Position,
+    /// U and V of [DicedSprite::uvs].
+    Uv,
+}
+
+/// Numeric format of [VertexElement] components; all are little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexFormat {
+    /// 32-bit float; 8 bytes per element.
+    F32,
+    /// 16-bit (half precision) float; 4 bytes per element.
+    F16,
+    /// 16-bit unsigned integer mapped to 0.0-1.0 range; 4 bytes per element. Not applicable to
+    /// positions, which are not normalized.
+    Unorm16,
+}
+
+impl VertexFormat {
+    /// Size of an element with the format, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            VertexFormat::F32 => 8,
+            VertexFormat::F16 | VertexFormat::Unorm16 => 4,
+        }
+    }
+}
+
+/// Diced sprite meshes written into a [VertexLayout].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexBuffer {
+    /// Interleaved vertex data, ready to be uploaded to GPU.
+    pub vertices: Vec<u8>,
+    /// Face indices to the vertices of all the written sprites.
+    pub indices: Indices,
+    /// Ranges of the written sprites in the buffers, in order of the sprites.
+    pub ranges: Vec<BufferRange>,
+}
+
+/// Range of a sprite written into [VertexBuffer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferRange {
+    /// Index of the first sprite vertex in the buffer.
+    pub first_vertex: usize,
+    /// Number of the sprite vertices.
+    pub vertex_count: usize,
+    /// Position of the first sprite index in the buffer. The indices are offset by
+    /// [first_vertex], so they refer to the whole vertex buffer.
+    pub first_index: usize,
+    /// Number of the sprite indices.
+    pub index_count: usize,
+}
+
 /// Product of dicing a [SourceSprite]'s texture.
 #[derive(Debug, Clone)]
 pub(crate) struct DicedTexture {
@@ -265,10 +765,28 @@ pub(crate) struct DicedTexture {
     pub size: USize,
     /// Pivot of the associated [SourceSprite], if any.
     pub pivot: Option<Pivot>,
+    /// Origin of the associated [SourceSprite], if any.
+    pub origin: Option<Origin>,
+    /// Group of the associated [SourceSprite], if any.
+    pub group: Option<String>,
+    /// PPU of the associated [SourceSprite], if any.
+    pub ppu: Option<f32>,
+    /// Outline of the source texture's opaque region, in pixels; empty when not built.
+    pub outline: Vec<Vec<Vertex>>,
+    /// Nine-slice borders of the associated [SourceSprite], if any.
+    pub border: Option<Border>,
     /// Associated diced units.
     pub units: Vec<DicedUnit>,
-    /// Hashes of diced units with distinct content.
+    /// Hashes of diced units with distinct content, which are to be packed.
     pub unique: HashSet<u64>,
+    /// Multiple of the unit size the full-detail units are diced with; above 1 when the texture
+    /// is coarsened to fit [Prefs::vertex_budget], in which case each unit spans multiple cells.
+    pub scale: u32,
+    /// Whether the texture is packed whole, in which case it has a single unit covering
+    /// trimmed texture rect.
+    pub whole: bool,
+    /// Identifiers of the source sprites identical to the associated [SourceSprite].
+    pub aliases: Vec<String>,
 }
 
 /// A chunk diced from a source texture.
@@ -276,10 +794,17 @@ pub(crate) struct DicedTexture {
 pub(crate) struct DicedUnit {
     /// Position and dimensions of the unit inside source texture.
     pub rect: URect,
-    /// Unit pixels chopped from the source texture, including padding.
-    pub pixels: Vec<Pixel>,
+    /// Unit pixels chopped from the source texture, including padding. Shared by all the units
+    /// with identical content, in which case padding is chopped around the first diced unit.
+    pub pixels: Arc<[Pixel]>,
+    /// Dimensions of the unit pixels, including padding.
+    pub size: USize,
     /// Content hash based on the non-padded pixels of the unit.
     pub hash: u64,
+    /// Level of detail the unit belongs to: 0 for the full-detail units, otherwise the unit is
+    /// diced from the source texture downscaled by 2 to the power of the level, in which case
+    /// [rect] is in the downscaled texture space.
+    pub lod: u32,
 }
 
 /// Product of packing [DicedTexture]s.
@@ -289,6 +814,12 @@ pub(crate) struct Atlas {
     pub texture: Texture,
     /// Packed unit UV rects on the atlas texture, mapped by unit hashes.
     pub rects: HashMap<u64, FRect>,
+    /// Indexes of the atlas cells occupied by the packed units, mapped by unit hashes.
+    pub cells: HashMap<u64, u32>,
+    /// Number of columns and rows occupied by the units spanning multiple cells.
+    pub spans: HashMap<u64, USize>,
+    /// Group of the textures packed into this atlas.
+    pub group: Option<String>,
     /// Diced textures packed into this atlas.
     pub packed: Vec<DicedTexture>,
 }
@@ -307,7 +838,6 @@ pub(crate) struct URect {
 }
 
 impl URect {
-    #[allow(dead_code)] // Used in tests.
     pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
         URect {
             x,
@@ -382,4 +912,39 @@ mod tests {
         assert_eq!(pixel.b(), 3);
         assert_eq!(pixel.a(), 4);
     }
+
+    #[test]
+    fn indices_are_16_bit_when_fit() {
+        let indices = Indices::try_from(vec![0, 1, 65535]).unwrap();
+        assert_eq!(indices, Indices::U16(vec![0, 1, 65535]));
+    }
+
+    #[test]
+    fn indices_are_32_bit_when_dont_fit_16_bit() {
+        let indices = Indices::try_from(vec![0, 65536]).unwrap();
+        assert_eq!(indices, Indices::U32(vec![0, 65536]));
+        assert_eq!(indices.iter().collect::<Vec<_>>(), vec![0, 65536]);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn errs_when_indices_dont_fit_32_bit() {
+        let indices = Indices::try_from(vec![0, u32::MAX as usize + 1]);
+        assert!(indices.is_err_and(|e| e.to_string() == "Mesh index exceeds 32-bit range."));
+    }
+
+    #[test]
+    fn origin_is_evaluated_in_pixels() {
+        assert_eq!(Origin::Pixel(1.5, -2.0).to_pixel(4, 8), (1.5, -2.0));
+        assert_eq!(Origin::Anchor(Anchor::TopLeft).to_pixel(4, 8), (0.0, 0.0));
+        assert_eq!(Origin::Anchor(Anchor::Center).to_pixel(4, 8), (2.0, 4.0));
+        assert_eq!(
+            Origin::Anchor(Anchor::BottomCenter).to_pixel(4, 8),
+            (2.0, 8.0)
+        );
+        assert_eq!(
+            Origin::Anchor(Anchor::MiddleRight).to_pixel(4, 8),
+            (4.0, 4.0)
+        );
+    }
 }
@@ -0,0 +1,249 @@
+use crate::models::*;
+use std::collections::HashMap;
+
+/// Border of a pixel, as start position and direction.
+type Edge = ((i32, i32), (i32, i32));
+
+/// Traces closed paths around opaque regions of specified texture, simplified with specified
+/// tolerance. Positions are in pixels, counted from top-left corner of the texture. Outer paths
+/// are wound counter-clockwise and holes clockwise, as seen with Y axis pointing down.
+pub(crate) fn trace(tex: &Texture, threshold: u8, tolerance: f32) -> Vec<Vec<Vertex>> {
+    let edges = collect_edges(tex, threshold);
+    let mut outgoing: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (idx, (start, _)) in edges.iter().enumerate() {
+        outgoing.entry(*start).or_default().push(idx);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut paths = vec![];
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+        let mut path = vec![];
+        let mut idx = first;
+        loop {
+            used[idx] = true;
+            let (start, dir) = edges[idx];
+            path.push(start);
+            let end = (start.0 + dir.0, start.1 + dir.1);
+            idx = follow(&edges, &outgoing[&end], dir);
+            if idx == first {
+                break;
+            }
+        }
+        let path = remove_collinear(&path);
+        paths.push(simplify(&path, tolerance));
+    }
+    paths
+}
+
+/// Collects pixel edges separating opaque pixels from transparent ones, as start positions
+/// and directions; opaque pixel of each edge is on the same side relative to the direction.
+fn collect_edges(tex: &Texture, threshold: u8) -> Vec<Edge> {
+    let opaque = |x: i32, y: i32| {
+        x >= 0
+            && y >= 0
+            && x < tex.width as i32
+            && y < tex.height as i32
+            && tex.pixels[(x + y * tex.width as i32) as usize].a() >= threshold
+    };
+    let mut edges = vec![];
+    for y in 0..tex.height as i32 {
+        for x in 0..tex.width as i32 {
+            if !opaque(x, y) {
+                continue;
+            }
+            if !opaque(x, y - 1) {
+                edges.push(((x + 1, y), (-1, 0)));
+            }
+            if !opaque(x - 1, y) {
+                edges.push(((x, y), (0, 1)));
+            }
+            if !opaque(x, y + 1) {
+                edges.push(((x, y + 1), (1, 0)));
+            }
+            if !opaque(x + 1, y) {
+                edges.push(((x + 1, y + 1), (0, -1)));
+            }
+        }
+    }
+    edges
+}
+
+/// Picks edge to continue the path with. When two edges start at the same position (pixels
+/// touching diagonally), turns towards the opaque side, so the diagonal pixels are not joined.
+fn follow(edges: &[Edge], candidates: &[usize], dir: (i32, i32)) -> usize {
+    if candidates.len() == 1 {
+        return candidates[0];
+    }
+    let turn = (dir.1, -dir.0);
+    let found = candidates.iter().find(|idx| edges[**idx].1 == turn);
+    *found.unwrap_or(&candidates[0])
+}
+
+fn remove_collinear(path: &[(i32, i32)]) -> Vec<Vertex> {
+    let len = path.len();
+    let mut result = vec![];
+    for idx in 0..len {
+        let prev = path[(idx + len - 1) % len];
+        let cur = path[idx];
+        let next = path[(idx + 1) % len];
+        let cross = (cur.0 - prev.0) * (next.1 - cur.1) - (cur.1 - prev.1) * (next.0 - cur.0);
+        if cross != 0 {
+            result.push(Vertex::new(cur.0 as f32, cur.1 as f32));
+        }
+    }
+    result
+}
+
+/// Simplifies closed path with Ramer–Douglas–Peucker algorithm, splitting it into two chains
+/// at the most distant point; the path is returned as is when it would degenerate.
+fn simplify(path: &[Vertex], tolerance: f32) -> Vec<Vertex> {
+    if tolerance <= 0.0 || path.len() <= 3 {
+        return path.to_vec();
+    }
+    let far = (1..path.len())
+        .max_by(|a, b| distance(&path[0], &path[*a]).total_cmp(&distance(&path[0], &path[*b])))
+        .unwrap();
+    let first = simplify_chain(&path[..=far], tolerance);
+    let second = simplify_chain(&[&path[far..], &path[..1]].concat(), tolerance);
+    let mut simplified = first[..first.len() - 1].to_vec();
+    simplified.extend_from_slice(&second[..second.len() - 1]);
+    match simplified.len() < 3 {
+        true => path.to_vec(),
+        false => simplified,
+    }
+}
+
+fn simplify_chain(chain: &[Vertex], tolerance: f32) -> Vec<Vertex> {
+    let (first, last) = (&chain[0], &chain[chain.len() - 1]);
+    let mut max = 0.0;
+    let mut max_idx = 0;
+    for (idx, vertex) in chain.iter().enumerate().take(chain.len() - 1).skip(1) {
+        let dist = segment_distance(vertex, first, last);
+        if dist > max {
+            max = dist;
+            max_idx = idx;
+        }
+    }
+    if max <= tolerance {
+        return vec![first.to_owned(), last.to_owned()];
+    }
+    let mut simplified = simplify_chain(&chain[..=max_idx], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_chain(&chain[max_idx..], tolerance));
+    simplified
+}
+
+fn distance(a: &Vertex, b: &Vertex) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+fn segment_distance(point: &Vertex, start: &Vertex, end: &Vertex) -> f32 {
+    let length = distance(start, end);
+    if length == 0.0 {
+        return distance(point, start);
+    }
+    let cross = (end.x - start.x) * (start.y - point.y) - (start.x - point.x) * (end.y - start.y);
+    cross.abs() / length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::trace;
+    use crate::fixtures::*;
+    use crate::models::*;
+
+    #[test]
+    fn opaque_texture_is_outlined_with_rect() {
+        let paths = trace(&RGBY, 1, 0.0);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 4);
+        for corner in [(0.0, 0.0), (0.0, 2.0), (2.0, 2.0), (2.0, 0.0)] {
+            assert!(paths[0].contains(&Vertex::new(corner.0, corner.1)));
+        }
+    }
+
+    #[test]
+    fn transparent_texture_has_no_outline() {
+        assert!(trace(&TTTT, 1, 0.0).is_empty());
+    }
+
+    #[test]
+    fn outer_path_is_wound_counter_clockwise() {
+        let path = &trace(&B1X1, 1, 0.0)[0];
+        let start = path
+            .iter()
+            .position(|v| *v == Vertex::new(0.0, 0.0))
+            .unwrap();
+        assert_eq!(path[(start + 1) % 4], Vertex::new(0.0, 1.0));
+        assert_eq!(path[(start + 2) % 4], Vertex::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn concave_region_is_traced() {
+        let paths = trace(&BGRT, 1, 0.0);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 6);
+        assert!(paths[0].contains(&Vertex::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn diagonal_pixels_are_not_joined() {
+        let paths = trace(&BTGT, 1, 0.0);
+        assert_eq!(paths.len(), 1);
+        let tex = Texture {
+            width: 2,
+            height: 2,
+            pixels: vec![B, T, T, B],
+        };
+        let paths = trace(&tex, 1, 0.0);
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|p| p.len() == 4));
+    }
+
+    #[test]
+    fn holes_are_traced() {
+        let mut pixels = vec![B; 9];
+        pixels[4] = T;
+        let tex = Texture {
+            width: 3,
+            height: 3,
+            pixels,
+        };
+        let paths = trace(&tex, 1, 0.0);
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().any(|p| p.contains(&Vertex::new(1.0, 1.0))));
+    }
+
+    #[test]
+    fn pixels_below_threshold_are_ignored() {
+        let tex = Texture {
+            width: 2,
+            height: 1,
+            pixels: vec![B, Pixel::new(0, 0, 0, 100)],
+        };
+        assert_eq!(trace(&tex, 128, 0.0)[0].len(), 4);
+        assert!(trace(&tex, 128, 0.0)[0].contains(&Vertex::new(1.0, 0.0)));
+        assert!(trace(&tex, 100, 0.0)[0].contains(&Vertex::new(2.0, 0.0)));
+    }
+
+    #[test]
+    fn staircase_is_simplified_with_tolerance() {
+        let size = 8;
+        let pixels = (0..size * size).map(|i| match i % size <= i / size {
+            true => B,
+            false => T,
+        });
+        let tex = Texture {
+            width: size,
+            height: size,
+            pixels: pixels.collect(),
+        };
+        let detailed = &trace(&tex, 1, 0.0)[0];
+        let simplified = &trace(&tex, 1, 1.0)[0];
+        assert_eq!(detailed.len(), 2 * size as usize + 2);
+        assert_eq!(simplified.len(), 3);
+    }
+}
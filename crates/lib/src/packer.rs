@@ -1,8 +1,146 @@
 use crate::models::*;
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 /// Packs diced textures into atlases.
 pub(crate) fn pack(diced: Vec<DicedTexture>, prefs: &Prefs) -> Result<Vec<Atlas>> {
+    validate(prefs)?;
+
+    let total = diced.len();
+    let mut atlases = vec![];
+    for group in split_groups(diced) {
+        pack_group(group, prefs, total, &mut atlases)?;
+    }
+    if prefs.atlas_uniform {
+        uniform(&mut atlases, prefs);
+    }
+
+    Ok(atlases)
+}
+
+/// Packs diced textures into existing atlases, keeping previously packed units in place and
+/// filling free cells with the new units; textures that don't fit go to the new atlases.
+/// Returns all the atlases along with indexes of the modified and added ones.
+pub(crate) fn append(
+    diced: Vec<DicedTexture>,
+    previous: &Artifacts,
+    prefs: &Prefs,
+) -> Result<(Vec<Atlas>, Vec<usize>)> {
+    validate(prefs)?;
+    if previous.atlases.len() != previous.layouts.len() {
+        return Err(Error::Spec("Previous atlases don't match the layouts."));
+    }
+
+    let ctx = new_ctx(vec![], prefs);
+    let mut atlases = vec![];
+    for (texture, layout) in previous.atlases.iter().zip(&previous.layouts) {
+        if texture.width != layout.width || texture.height != layout.height {
+            return Err(Error::Spec("Previous atlases don't match the layouts."));
+        }
+        atlases.push(restore_atlas(&ctx, layout, texture.pixels.to_owned())?);
+    }
+
+    let total = diced.len();
+    let mut changed = HashSet::new();
+    let mut overflow = vec![];
+    for (idx, texture) in diced.into_iter().enumerate() {
+        Progress::report(prefs, 2, idx, total, "Appending units");
+        match find_fitting_atlas(&ctx, &atlases, &texture) {
+            Some(atlas_idx) => {
+                if append_texture(&ctx, &mut atlases[atlas_idx], texture)? {
+                    changed.insert(atlas_idx);
+                }
+            }
+            None => overflow.push(texture),
+        }
+    }
+
+    let first_new = atlases.len();
+    for group in split_groups(overflow) {
+        pack_group(group, prefs, total, &mut atlases)?;
+    }
+    changed.extend(first_new..atlases.len());
+
+    if prefs.atlas_uniform {
+        uniform_appended(&mut atlases, first_new, prefs)?;
+    }
+
+    let mut changed = changed.into_iter().collect::<Vec<_>>();
+    changed.sort_unstable();
+    Ok((atlases, changed))
+}
+
+/// Packs units of diced textures absent in the shipped atlases into new atlases, while the
+/// units present in the shipped atlases are referenced from there. Returned atlases start
+/// with the shipped ones, restored from the layouts without pixel content.
+pub(crate) fn patch(
+    diced: Vec<DicedTexture>,
+    shipped: &[Layout],
+    prefs: &Prefs,
+) -> Result<Vec<Atlas>> {
+    validate(prefs)?;
+
+    let ctx = new_ctx(vec![], prefs);
+    let mut atlases = vec![];
+    for layout in shipped {
+        atlases.push(restore_atlas(&ctx, layout, vec![])?);
+    }
+
+    let total = diced.len();
+    let mut to_pack = vec![];
+    for (idx, mut texture) in diced.into_iter().enumerate() {
+        Progress::report(prefs, 2, idx, total, "Referencing shipped units");
+        for unit in texture.units.iter() {
+            if let Some(atlas) = find_shipped_atlas(&mut atlases, &texture.group, unit.hash) {
+                let cell = atlas.cells[&unit.hash];
+                reference_unit(&ctx, atlas, unit, cell);
+                texture.unique.remove(&unit.hash);
+            }
+        }
+        if texture.unique.is_empty() {
+            let Some(hash) = texture.units.first().map(|u| u.hash) else {
+                return Err(Error::Spec("Can't patch a texture without units."));
+            };
+            let Some(atlas) = find_shipped_atlas(&mut atlases, &texture.group, hash) else {
+                return Err(Error::Spec(
+                    "Shipped atlas of a referenced unit is missing.",
+                ));
+            };
+            atlas.packed.push(texture);
+        } else {
+            to_pack.push(texture);
+        }
+    }
+
+    for group in split_groups(to_pack) {
+        pack_group(group, prefs, total, &mut atlases)?;
+    }
+    if prefs.atlas_uniform {
+        uniform_appended(&mut atlases, shipped.len(), prefs)?;
+    }
+
+    Ok(atlases)
+}
+
+/// Evaluates placement of the units packed into specified atlas.
+pub(crate) fn layout(atlas: &Atlas, prefs: &Prefs) -> Layout {
+    let padded_unit_size = prefs.unit_size + prefs.padding * 2;
+    Layout {
+        group: atlas.group.to_owned(),
+        width: atlas.texture.width,
+        height: atlas.texture.height,
+        columns: atlas.texture.width / padded_unit_size,
+        rows: atlas.texture.height / padded_unit_size,
+        cells: atlas.cells.to_owned(),
+        spans: atlas
+            .spans
+            .iter()
+            .map(|(h, s)| (*h, (s.width, s.height)))
+            .collect(),
+    }
+}
+
+fn validate(prefs: &Prefs) -> Result<()> {
     if prefs.uv_inset > 0.5 {
         return Err(Error::Spec("UV inset should be in 0.0 to 0.5 range."));
     }
@@ -12,18 +150,40 @@ pub(crate) fn pack(diced: Vec<DicedTexture>, prefs: &Prefs) -> Result<Vec<Atlas>
     if prefs.unit_size > prefs.atlas_size_limit {
         return Err(Error::Spec("Unit size can't be above atlas size limit."));
     }
+    Ok(())
+}
 
-    let total = diced.len();
-    let mut atlases = vec![];
-    let mut ctx = new_ctx(diced, prefs);
+/// Splits diced textures by groups, preserving order in which the groups first appear.
+pub(crate) fn split_groups(diced: Vec<DicedTexture>) -> Vec<Vec<DicedTexture>> {
+    let mut groups: Vec<Vec<DicedTexture>> = vec![];
+    for texture in diced {
+        match groups.iter_mut().find(|g| g[0].group == texture.group) {
+            Some(group) => group.push(texture),
+            None => groups.push(vec![texture]),
+        }
+    }
+    groups
+}
+
+fn pack_group(
+    group: Vec<DicedTexture>,
+    prefs: &Prefs,
+    total: usize,
+    atlases: &mut Vec<Atlas>,
+) -> Result<()> {
+    let mut ctx = new_ctx(group, prefs);
+    if prefs.atlas_balance {
+        ctx.unit_capacity = eval_balanced_capacity(&ctx, prefs);
+    }
     while !ctx.to_pack.is_empty() {
-        Progress::report(prefs, 2, total - ctx.to_pack.len(), total, "Packing units");
+        let done = atlases.iter().map(|a| a.packed.len()).sum();
+        Progress::report(prefs, 2, done, total, "Packing units");
         atlases.push(pack_it(&mut ctx)?);
         ctx.packed.clear();
+        ctx.order.clear();
         ctx.units.clear();
     }
-
-    Ok(atlases)
+    Ok(())
 }
 
 struct Context {
@@ -34,12 +194,14 @@ struct Context {
     unit_size: u32,
     pad: u32,
     padded_unit_size: u32,
-    /// Max. number of units single atlas is able to accommodate.
+    /// Max. number of cells single atlas is able to accommodate.
     unit_capacity: u32,
     /// Total textures left to pack.
     to_pack: Vec<DicedTexture>,
     /// Indexes of to_pack textures packed into current atlas.
     packed: HashSet<usize>,
+    /// Indexes of to_pack textures packed into current atlas, in order of packing.
+    order: Vec<usize>,
     /// Units packed into current atlas mapped by hashes.
     units: HashMap<u64, UnitRef>,
 }
@@ -66,64 +228,201 @@ fn new_ctx(diced: Vec<DicedTexture>, prefs: &Prefs) -> Context {
         unit_capacity,
         to_pack: diced,
         packed: HashSet::new(),
+        order: vec![],
         units: HashMap::new(),
     }
 }
 
 fn pack_it(ctx: &mut Context) -> Result<Atlas> {
-    while let Some(tex_idx) = find_packable_texture(ctx) {
-        ctx.packed.insert(tex_idx);
-        let units = ctx.to_pack[tex_idx].units.iter().enumerate();
-        let refs = units.map(|(unit_idx, u)| (u.hash, UnitRef { tex_idx, unit_idx }));
-        ctx.units.extend(refs);
-    }
-
-    if ctx.packed.is_empty() {
+    let Some((atlas_size, cells)) = fit_atlas(ctx) else {
         return Err(Error::Spec(
             "Can't fit single texture; increase atlas size limit.",
         ));
-    }
+    };
 
-    let atlas_size = eval_atlas_size(ctx);
-    let (texture, rects) = bake_atlas(ctx, &atlas_size);
+    let (texture, rects, spans) = bake_atlas(ctx, &atlas_size, &cells);
     let packed = extract_packed_textures(ctx);
 
     Ok(Atlas {
         texture,
         rects,
+        cells,
+        spans,
+        group: packed[0].group.to_owned(),
         packed,
     })
 }
 
-fn find_packable_texture(ctx: &Context) -> Option<usize> {
-    let mut optimal_texture_idx: Option<usize> = None;
-    let mut min_units_to_pack = u32::MAX;
+/// Fills current atlas with textures and places their units; returns [None] when not even
+/// a single texture fits.
+fn fit_atlas(ctx: &mut Context) -> Option<(USize, HashMap<u64, u32>)> {
+    fill_atlas(ctx);
+    loop {
+        if ctx.packed.is_empty() {
+            return None;
+        }
+        if let Some(placement) = place_units(ctx) {
+            return Some(placement);
+        }
+        // Spanning units may not fit due to fragmentation, in which case the textures
+        // with such units (whole or coarsened) are moved to the next atlas one by one.
+        evict_spanning_texture(ctx);
+    }
+}
 
+/// Packs textures into current atlas while there is room, picking on each step the texture
+/// with the least cells required by units not yet packed into the atlas; ties are resolved in
+/// favor of the texture with lower index. Counts of the missing cells are updated incrementally
+/// via an inverted index of the unit hashes, so that each insertion only visits the textures
+/// sharing the inserted units, instead of re-scanning all the remaining textures. The index and
+/// the counts are rebuilt for each atlas, as the counts depend on the atlas content, hence the
+/// total cost is proportional to the number of atlases times the units of the textures left
+/// to pack; it's near-linear while the atlases are few, but not with many small atlases.
+fn fill_atlas(ctx: &mut Context) {
+    let mut owners: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut missing = vec![0; ctx.to_pack.len()];
+    let mut queue = BTreeSet::new();
     for (idx, texture) in ctx.to_pack.iter().enumerate() {
         if ctx.packed.contains(&idx) {
             continue;
         }
-        let units_to_pack = texture
-            .unique
-            .iter()
-            .filter(|u| !ctx.units.contains_key(u))
-            .count() as u32;
-        if units_to_pack < min_units_to_pack {
-            optimal_texture_idx = Some(idx);
-            min_units_to_pack = units_to_pack;
+        for hash in texture.unique.iter() {
+            owners.entry(*hash).or_default().push(idx);
         }
+        missing[idx] = count_missing_cells(ctx, texture);
+        queue.insert((missing[idx], idx));
     }
 
-    optimal_texture_idx?;
-    if (ctx.units.len() as u32 + min_units_to_pack) <= ctx.unit_capacity {
-        optimal_texture_idx
-    } else {
-        None
+    let mut used = count_used_cells(ctx);
+    while let Some(&(count, tex_idx)) = queue.first() {
+        if (used + count) as u32 > ctx.unit_capacity {
+            break;
+        }
+        queue.pop_first();
+        for (hash, cells) in add_units(ctx, tex_idx) {
+            used += cells;
+            for owner in owners[&hash].iter().copied() {
+                if queue.remove(&(missing[owner], owner)) {
+                    missing[owner] -= cells;
+                    queue.insert((missing[owner], owner));
+                }
+            }
+        }
+    }
+}
+
+/// Adds units of the texture under specified index to the current atlas; returns hashes of
+/// the units, which were not yet in the atlas, along with the number of cells they occupy.
+fn add_units(ctx: &mut Context, tex_idx: usize) -> Vec<(u64, usize)> {
+    ctx.packed.insert(tex_idx);
+    ctx.order.push(tex_idx);
+    let mut added = vec![];
+    let texture = &ctx.to_pack[tex_idx];
+    for (unit_idx, unit) in texture.units.iter().enumerate() {
+        if !texture.unique.contains(&unit.hash) {
+            continue;
+        }
+        let unit_ref = UnitRef { tex_idx, unit_idx };
+        if ctx.units.insert(unit.hash, unit_ref).is_none() {
+            added.push((unit.hash, eval_area(ctx, unit)));
+        }
+    }
+    added
+}
+
+/// Removes last packed texture with units spanning multiple cells from the current atlas, if any.
+fn evict_spanning_texture(ctx: &mut Context) {
+    let spanning = ctx.order.iter().rposition(|idx| {
+        let texture = &ctx.to_pack[*idx];
+        texture.whole || texture.scale > 1
+    });
+    let Some(pos) = spanning else {
+        ctx.packed.clear();
+        return;
+    };
+    let order = std::mem::take(&mut ctx.order);
+    ctx.packed.clear();
+    ctx.units.clear();
+    for (idx, tex_idx) in order.into_iter().enumerate() {
+        if idx != pos {
+            add_units(ctx, tex_idx);
+        }
+    }
+}
+
+/// Counts cells required to fit units of the texture, which are not yet in the current atlas.
+fn count_missing_cells(ctx: &Context, texture: &DicedTexture) -> usize {
+    let mut counted = HashSet::new();
+    let mut count = 0;
+    for unit in texture.units.iter() {
+        if texture.unique.contains(&unit.hash)
+            && !ctx.units.contains_key(&unit.hash)
+            && counted.insert(unit.hash)
+        {
+            count += eval_area(ctx, unit);
+        }
+    }
+    count
+}
+
+/// Counts cells occupied by the units in the current atlas.
+fn count_used_cells(ctx: &Context) -> usize {
+    let units = ctx.units.values();
+    units
+        .map(|r| eval_area(ctx, &ctx.to_pack[r.tex_idx].units[r.unit_idx]))
+        .sum()
+}
+
+/// Evaluates number of columns and rows occupied by specified unit.
+fn eval_span(ctx: &Context, unit: &DicedUnit) -> USize {
+    let width = (unit.size.width - ctx.pad * 2).div_ceil(ctx.unit_size);
+    let height = (unit.size.height - ctx.pad * 2).div_ceil(ctx.unit_size);
+    USize::new(width, height)
+}
+
+fn eval_area(ctx: &Context, unit: &DicedUnit) -> usize {
+    let span = eval_span(ctx, unit);
+    (span.width * span.height) as usize
+}
+
+/// Finds minimum unit capacity of the atlases, which doesn't increase the number of atlases
+/// required to fit the textures, so that the units are distributed evenly over the atlases.
+fn eval_balanced_capacity(ctx: &Context, prefs: &Prefs) -> u32 {
+    let Some(count) = count_atlases(ctx, prefs, ctx.unit_capacity) else {
+        return ctx.unit_capacity;
+    };
+    let (mut min, mut max) = (1, ctx.unit_capacity);
+    while min < max {
+        let capacity = min + (max - min) / 2;
+        match count_atlases(ctx, prefs, capacity) {
+            Some(n) if n <= count => max = capacity,
+            _ => min = capacity + 1,
+        }
     }
+    max
+}
+
+/// Simulates packing with specified unit capacity and returns the number of atlases required
+/// to fit the textures or [None] when a texture doesn't fit. Units are placed the same way as
+/// when packing, so that the textures evicted due to spanning units are accounted.
+fn count_atlases(ctx: &Context, prefs: &Prefs, capacity: u32) -> Option<usize> {
+    // Cloning is cheap, as the unit pixels are shared.
+    let mut sim = new_ctx(ctx.to_pack.to_owned(), prefs);
+    sim.unit_capacity = capacity;
+    let mut count = 0;
+    while !sim.to_pack.is_empty() {
+        fit_atlas(&mut sim)?;
+        extract_packed_textures(&mut sim);
+        sim.packed.clear();
+        sim.order.clear();
+        sim.units.clear();
+        count += 1;
+    }
+    Some(count)
 }
 
 fn eval_atlas_size(ctx: &Context) -> USize {
-    let units_count = ctx.units.len() as u32;
+    let units_count = count_used_cells(ctx) as u32;
     let size = (units_count as f32).sqrt().ceil() as u32;
 
     if ctx.pot {
@@ -153,44 +452,124 @@ fn eval_atlas_size(ctx: &Context) -> USize {
     )
 }
 
-fn bake_atlas(ctx: &Context, size: &USize) -> (Texture, HashMap<u64, FRect>) {
+/// Evaluates atlas size and cells of the units in the current atlas; returns [None] when
+/// the units don't fit under the atlas size limit.
+fn place_units(ctx: &Context) -> Option<(USize, HashMap<u64, u32>)> {
+    let mut size = eval_atlas_size(ctx);
+    loop {
+        if let Some(cells) = arrange_units(ctx, &size) {
+            return Some((size, cells));
+        }
+        size = grow_atlas(ctx, &size)?;
+    }
+}
+
+/// Assigns cells to the units in the current atlas, placing spanning units first, the
+/// tallest first, and filling the remaining cells with single-cell units left to right,
+/// top to bottom. Returns [None] when the units don't fit in specified atlas size.
+fn arrange_units(ctx: &Context, size: &USize) -> Option<HashMap<u64, u32>> {
+    let columns = size.width / ctx.padded_unit_size;
+    let rows = size.height / ctx.padded_unit_size;
+
+    // Hash containers in Rust intentionally randomize order for security, while we need
+    // stable order to produce identical atlases for identical input, hence the sorting here.
+    let mut sorted_hashes = ctx.units.keys().collect::<Vec<_>>();
+    sorted_hashes.sort_unstable();
+    let spans = sorted_hashes.into_iter().map(|hash| {
+        let unit_ref = &ctx.units[hash];
+        let unit = &ctx.to_pack[unit_ref.tex_idx].units[unit_ref.unit_idx];
+        (*hash, eval_span(ctx, unit))
+    });
+    let mut spans = spans.collect::<Vec<_>>();
+    spans.sort_by_key(|(_, span)| (Reverse(span.height), Reverse(span.width)));
+
+    let mut occupied = vec![false; (columns * rows) as usize];
+    let mut cells = HashMap::new();
+    let mut first_free = 0;
+    for (hash, span) in spans {
+        let cell = find_free_cells(&occupied, columns, &span, first_free)?;
+        for row in cell / columns..cell / columns + span.height {
+            let start = (row * columns + cell % columns) as usize;
+            occupied[start..start + span.width as usize].fill(true);
+        }
+        if span.width * span.height == 1 {
+            first_free = cell;
+        }
+        cells.insert(hash, cell);
+    }
+    Some(cells)
+}
+
+/// Finds top-left cell of the free area with specified span, starting from specified cell.
+fn find_free_cells(occupied: &[bool], columns: u32, span: &USize, from: u32) -> Option<u32> {
+    let rows = occupied.len() as u32 / columns.max(1);
+    (from..occupied.len() as u32).find(|cell| {
+        let (column, row) = (cell % columns, cell / columns);
+        column + span.width <= columns
+            && row + span.height <= rows
+            && (row..row + span.height).all(|r| {
+                let start = (r * columns + column) as usize;
+                !occupied[start..start + span.width as usize].contains(&true)
+            })
+    })
+}
+
+/// Evaluates next larger atlas size honoring the preferences; returns [None] when above limit.
+fn grow_atlas(ctx: &Context, size: &USize) -> Option<USize> {
+    let step = ctx.padded_unit_size;
+    let limit = ctx.size_limit;
+    let (width, height) = if ctx.pot {
+        (size.width * 2, size.height * 2)
+    } else if ctx.square {
+        (size.width + step, size.height + step)
+    } else if size.width <= size.height && size.width + step <= limit {
+        (size.width + step, size.height)
+    } else if size.height + step <= limit {
+        (size.width, size.height + step)
+    } else {
+        (size.width + step, size.height)
+    };
+    (width <= limit && height <= limit).then(|| USize::new(width, height))
+}
+
+fn bake_atlas(
+    ctx: &Context,
+    size: &USize,
+    cells: &HashMap<u64, u32>,
+) -> (Texture, HashMap<u64, FRect>, HashMap<u64, USize>) {
     let units_per_row = size.width / ctx.padded_unit_size;
     let mut rects = HashMap::new();
+    let mut spans = HashMap::new();
     let mut texture = Texture {
         width: size.width,
         height: size.height,
         pixels: vec![Pixel::default(); (size.width * size.height) as usize],
     };
 
-    // Hash containers in Rust intentionally randomize order for security, while we need
-    // stable order to produce identical atlases for identical input, hence the sorting here.
-    let mut sorted_hashes = ctx.units.keys().collect::<Vec<_>>();
-    sorted_hashes.sort_unstable();
-
-    for (unit_idx, unit_hash) in sorted_hashes.into_iter().enumerate() {
+    for (unit_hash, cell) in cells.iter() {
         let unit_ref = &ctx.units[unit_hash];
-        let row = unit_idx as u32 / units_per_row;
-        let column = unit_idx as u32 % units_per_row;
+        let row = cell / units_per_row;
+        let column = cell % units_per_row;
         let unit = &ctx.to_pack[unit_ref.tex_idx].units[unit_ref.unit_idx];
-        set_pixels(ctx, &unit.pixels, column, row, &mut texture);
-
-        let rect = get_uv(ctx, column, row, size);
-        let rect = inset_uv(ctx, rect);
-        let rect = scale_uv(ctx, rect, unit);
-        rects.insert(*unit_hash, rect);
+        set_pixels(ctx, unit, column, row, &mut texture);
+        rects.insert(*unit_hash, eval_uv(ctx, column, row, size, unit));
+        let span = eval_span(ctx, unit);
+        if span.width * span.height > 1 {
+            spans.insert(*unit_hash, span);
+        }
     }
 
-    (texture, rects)
+    (texture, rects, spans)
 }
 
-fn set_pixels(ctx: &Context, pixels: &[Pixel], column: u32, row: u32, atlas: &mut Texture) {
+fn set_pixels(ctx: &Context, unit: &DicedUnit, column: u32, row: u32, atlas: &mut Texture) {
     let mut from_idx = 0;
     let start_x = column * ctx.padded_unit_size;
     let start_y = row * ctx.padded_unit_size;
-    for y in start_y..(start_y + ctx.padded_unit_size) {
-        for x in start_x..(start_x + ctx.padded_unit_size) {
+    for y in start_y..(start_y + unit.size.height) {
+        for x in start_x..(start_x + unit.size.width) {
             let into_idx = (x + atlas.width * y) as usize;
-            atlas.pixels[into_idx] = pixels[from_idx];
+            atlas.pixels[into_idx] = unit.pixels[from_idx];
             from_idx += 1;
         }
     }
@@ -204,6 +583,12 @@ fn get_uv(ctx: &Context, column: u32, row: u32, atlas_size: &USize) -> FRect {
     FRect::new(x, y, width, height)
 }
 
+fn eval_uv(ctx: &Context, column: u32, row: u32, atlas_size: &USize, unit: &DicedUnit) -> FRect {
+    let rect = get_uv(ctx, column, row, atlas_size);
+    let rect = inset_uv(ctx, rect);
+    scale_uv(ctx, rect, unit)
+}
+
 fn inset_uv(ctx: &Context, rect: FRect) -> FRect {
     let d = ctx.inset * (rect.width / 2.0);
     let dx2 = d * 2.0;
@@ -216,6 +601,231 @@ fn scale_uv(ctx: &Context, rect: FRect, unit: &DicedUnit) -> FRect {
     FRect::new(rect.x, rect.y, rect.width * mx, rect.height * my)
 }
 
+/// Expands atlas textures of each group to the dimensions of the group's largest atlas,
+/// keeping content at top-left. Returns indexes of the expanded atlases.
+pub(crate) fn uniform(atlases: &mut [Atlas], prefs: &Prefs) -> Vec<usize> {
+    let mut expanded = vec![];
+    for idx in 0..atlases.len() {
+        let group = &atlases[idx].group;
+        let grouped = atlases.iter().filter(|a| &a.group == group);
+        let mut width = grouped.clone().map(|a| a.texture.width).max().unwrap_or(0);
+        let mut height = grouped.map(|a| a.texture.height).max().unwrap_or(0);
+        if prefs.atlas_square {
+            width = width.max(height);
+            height = width;
+        }
+        if expand(&mut atlases[idx], width, height, prefs) {
+            expanded.push(idx);
+        }
+    }
+    expanded
+}
+
+/// Expands atlases starting at specified index to the dimensions of the previous (appended
+/// to or shipped) atlases of the same group, so that the previous atlases (and sprites
+/// referencing them) are kept intact; groups without previous atlases are made uniform
+/// among the new atlases.
+fn uniform_appended(atlases: &mut [Atlas], first_new: usize, prefs: &Prefs) -> Result<()> {
+    for idx in first_new..atlases.len() {
+        let group = &atlases[idx].group;
+        let previous = atlases[..first_new].iter().find(|a| &a.group == group);
+        let Some(previous) = previous.map(|a| USize::new(a.texture.width, a.texture.height)) else {
+            continue;
+        };
+        let texture = &atlases[idx].texture;
+        if texture.width > previous.width || texture.height > previous.height {
+            return Err(Error::Spec(
+                "New atlas exceeds dimensions of the previous uniform atlases.",
+            ));
+        }
+        expand(&mut atlases[idx], previous.width, previous.height, prefs);
+    }
+    uniform(&mut atlases[first_new..], prefs);
+    Ok(())
+}
+
+/// Expands atlas texture to specified dimensions; returns false when already of the size.
+fn expand(atlas: &mut Atlas, width: u32, height: u32, prefs: &Prefs) -> bool {
+    let padded_unit_size = prefs.unit_size + prefs.padding * 2;
+    let size = USize::new(atlas.texture.width, atlas.texture.height);
+    if size.width == width && size.height == height {
+        return false;
+    }
+    let mut pixels = vec![Pixel::default(); (width * height) as usize];
+    for (y, row) in atlas.texture.pixels.chunks(size.width as usize).enumerate() {
+        let start = y * width as usize;
+        pixels[start..start + row.len()].copy_from_slice(row);
+    }
+    atlas.texture = Texture {
+        width,
+        height,
+        pixels,
+    };
+    let mx = size.width as f32 / width as f32;
+    let my = size.height as f32 / height as f32;
+    for rect in atlas.rects.values_mut() {
+        *rect = FRect::new(rect.x * mx, rect.y * my, rect.width * mx, rect.height * my);
+    }
+    let old_columns = size.width / padded_unit_size;
+    let new_columns = width / padded_unit_size;
+    for cell in atlas.cells.values_mut() {
+        *cell = (*cell / old_columns) * new_columns + *cell % old_columns;
+    }
+    true
+}
+
+/// Re-creates atlas from a previously generated layout and atlas texture pixels.
+fn restore_atlas(ctx: &Context, layout: &Layout, pixels: Vec<Pixel>) -> Result<Atlas> {
+    check_layout(layout, ctx.padded_unit_size)?;
+    Ok(Atlas {
+        texture: Texture {
+            width: layout.width,
+            height: layout.height,
+            pixels,
+        },
+        rects: HashMap::new(),
+        cells: layout.cells.to_owned(),
+        spans: layout
+            .spans
+            .iter()
+            .map(|(h, s)| (*h, USize::new(s.0, s.1)))
+            .collect(),
+        group: layout.group.to_owned(),
+        packed: vec![],
+    })
+}
+
+/// Checks whether cell grid of specified layout fits the atlas dimensions and all the cells,
+/// including the spanning ones, fit the grid. The layouts may come from external data,
+/// hence the arithmetic is checked. Layouts of the atlases packed whole have no cell grid
+/// (see [crate::pack]) and are rejected, as their packed area can't be told from free cells.
+pub(crate) fn check_layout(layout: &Layout, padded_unit_size: u32) -> Result<()> {
+    if layout.columns == 0 || layout.rows == 0 {
+        return Err(Error::Spec(
+            "Layout has no cell grid; atlases packed whole can't be reused.",
+        ));
+    }
+    let fits = |cells: u32, size: u32| {
+        let len = cells.checked_mul(padded_unit_size);
+        len.is_some_and(|len| len <= size)
+    };
+    if !fits(layout.columns, layout.width) || !fits(layout.rows, layout.height) {
+        return Err(Error::Spec(
+            "Previous layout doesn't match unit size and padding.",
+        ));
+    }
+    let (columns, rows) = (layout.columns as u64, layout.rows as u64);
+    let in_grid = layout.cells.values().all(|c| (*c as u64) < columns * rows);
+    let spans_in_grid = || {
+        layout
+            .spans
+            .iter()
+            .all(|(hash, (span_columns, span_rows))| {
+                layout.cells.get(hash).is_some_and(|c| {
+                    let (column, row) = (*c as u64 % columns, *c as u64 / columns);
+                    column + *span_columns as u64 <= columns && row + *span_rows as u64 <= rows
+                })
+            })
+    };
+    if !in_grid || !spans_in_grid() {
+        return Err(Error::Spec("Previous layout is corrupted."));
+    }
+    Ok(())
+}
+
+/// Finds first atlas of specified group containing unit with specified hash.
+fn find_shipped_atlas<'a>(
+    atlases: &'a mut [Atlas],
+    group: &Option<String>,
+    hash: u64,
+) -> Option<&'a mut Atlas> {
+    let mut atlases = atlases.iter_mut();
+    atlases.find(|a| &a.group == group && a.cells.contains_key(&hash))
+}
+
+/// Finds atlas of the texture's group with enough free cells to accommodate the texture's
+/// units, preferring the one which already contains most of them.
+fn find_fitting_atlas(ctx: &Context, atlases: &[Atlas], texture: &DicedTexture) -> Option<usize> {
+    let mut optimal_atlas_idx = None;
+    let mut min_units_to_pack = usize::MAX;
+    for (idx, atlas) in atlases.iter().enumerate() {
+        if atlas.group != texture.group {
+            continue;
+        }
+        // Spanning units would require searching for a free area, hence packing them anew.
+        let mut missing = texture
+            .units
+            .iter()
+            .filter(|u| !atlas.cells.contains_key(&u.hash));
+        if missing.any(|u| eval_area(ctx, u) > 1) {
+            continue;
+        }
+        let capacity = count_cells(ctx, &atlas.texture) as usize;
+        let units_to_pack = texture
+            .unique
+            .iter()
+            .filter(|u| !atlas.cells.contains_key(u))
+            .count();
+        let spanned = atlas
+            .spans
+            .values()
+            .map(|s| (s.width * s.height - 1) as usize);
+        let occupied = atlas.cells.len() + spanned.sum::<usize>();
+        if units_to_pack < min_units_to_pack && occupied + units_to_pack <= capacity {
+            optimal_atlas_idx = Some(idx);
+            min_units_to_pack = units_to_pack;
+        }
+    }
+    optimal_atlas_idx
+}
+
+/// Writes units of the texture missing in the atlas into free cells of the atlas.
+/// Returns whether any units were written or [Error] when the atlas runs out of free cells.
+fn append_texture(ctx: &Context, atlas: &mut Atlas, texture: DicedTexture) -> Result<bool> {
+    let columns = atlas.texture.width / ctx.padded_unit_size;
+    let mut occupied = HashSet::new();
+    for (hash, cell) in atlas.cells.iter() {
+        let span = atlas.spans.get(hash).cloned().unwrap_or(USize::new(1, 1));
+        for row in cell / columns..cell / columns + span.height {
+            let start = row * columns + cell % columns;
+            occupied.extend(start..start + span.width);
+        }
+    }
+    let mut free = (0..count_cells(ctx, &atlas.texture)).filter(|c| !occupied.contains(c));
+    let mut written = false;
+    for unit in texture.units.iter() {
+        let cell = match atlas.cells.get(&unit.hash) {
+            Some(cell) => *cell,
+            None => {
+                let Some(cell) = free.next() else {
+                    return Err(Error::Spec("Appended atlas has no free cells left."));
+                };
+                let (column, row) = (cell % columns, cell / columns);
+                set_pixels(ctx, unit, column, row, &mut atlas.texture);
+                atlas.cells.insert(unit.hash, cell);
+                written = true;
+                cell
+            }
+        };
+        reference_unit(ctx, atlas, unit, cell);
+    }
+    atlas.packed.push(texture);
+    Ok(written)
+}
+
+/// Maps UV rect of the unit packed into specified atlas cell, unless already mapped.
+fn reference_unit(ctx: &Context, atlas: &mut Atlas, unit: &DicedUnit, cell: u32) {
+    let size = USize::new(atlas.texture.width, atlas.texture.height);
+    let columns = size.width / ctx.padded_unit_size;
+    let (column, row) = (cell % columns, cell / columns);
+    let rect = eval_uv(ctx, column, row, &size, unit);
+    atlas.rects.entry(unit.hash).or_insert(rect);
+}
+
+fn count_cells(ctx: &Context, texture: &Texture) -> u32 {
+    (texture.width / ctx.padded_unit_size) * (texture.height / ctx.padded_unit_size)
+}
+
 fn extract_packed_textures(ctx: &mut Context) -> Vec<DicedTexture> {
     let mut packed = Vec::new();
     let mut idx = ctx.to_pack.len() - 1;
@@ -235,6 +845,8 @@ fn extract_packed_textures(ctx: &mut Context) -> Vec<DicedTexture> {
 mod tests {
     use crate::fixtures::*;
     use crate::models::*;
+    use crate::packer::{append_texture, layout, new_ctx};
+    use std::collections::HashSet;
 
     #[test]
     fn can_pack_with_defaults() {
@@ -342,6 +954,55 @@ mod tests {
         assert_eq!(atlas.texture.height, 4);
     }
 
+    #[test]
+    fn when_uniform_atlases_have_identical_size() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let atlases = pack(vec![&C1X1, &RGBY], &prefs);
+        assert_eq!(atlases.len(), 2);
+        assert!(atlases.iter().all(|a| a.texture.width == 2));
+        assert!(atlases.iter().all(|a| a.texture.height == 2));
+    }
+
+    #[test]
+    fn when_uniform_expanded_atlas_uvs_are_scaled() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let atlas = pack(vec![&C1X1, &RGBY], &prefs).remove(0);
+        let rect = atlas.rects.values().next().unwrap();
+        assert_eq!(*rect, FRect::new(0.0, 0.0, 0.5, 0.5));
+        assert_eq!(atlas.texture.pixels, vec![C, T, T, T]);
+    }
+
+    #[test]
+    fn when_not_uniform_atlases_have_individual_size() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            ..defaults()
+        };
+        let atlases = pack(vec![&C1X1, &RGBY], &prefs);
+        assert_eq!(atlases[0].texture.width, 1);
+        assert_eq!(atlases[1].texture.width, 2);
+    }
+
+    #[test]
+    fn uniform_size_is_evaluated_per_group() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let atlases = pack(vec![&(&C1X1, "a"), &(&RGBY, "b")], &prefs);
+        assert_eq!(atlases[0].texture.width, 1);
+        assert_eq!(atlases[1].texture.width, 2);
+    }
+
     #[test]
     fn unused_pixels_are_clear() {
         let prefs = Prefs {
@@ -384,18 +1045,561 @@ mod tests {
         assert_eq!(*rect, FRect::new(0.25, 0.25, 0.25, 0.25));
     }
 
+    #[test]
+    fn sprites_of_same_group_are_packed_together() {
+        let atlases = pack(vec![&(&B1X1, "a"), &(&R1X1, "a")], &defaults());
+        assert_eq!(atlases.len(), 1);
+    }
+
+    #[test]
+    fn sprites_of_distinct_groups_are_not_packed_together() {
+        let atlases = pack(vec![&(&B1X1, "a"), &(&R1X1, "b"), &G1X1], &defaults());
+        assert_eq!(atlases.len(), 3);
+        assert!(atlases.iter().all(|a| a.packed.len() == 1));
+    }
+
+    #[test]
+    fn identical_units_are_duplicated_across_groups() {
+        let atlases = pack(vec![&(&B1X1, "a"), &(&B1X1, "b")], &defaults());
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(atlases[0].rects.len(), 1);
+        assert_eq!(atlases[1].rects.len(), 1);
+    }
+
+    #[test]
+    fn each_group_produces_own_atlas_series() {
+        let prefs = Prefs {
+            atlas_size_limit: 1,
+            ..defaults()
+        };
+        let groups = pack(vec![&(&B1X1, "a"), &(&R1X1, "b"), &(&G1X1, "a")], &prefs)
+            .into_iter()
+            .map(|a| a.packed[0].group.to_owned().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec!["a", "a", "b"]);
+    }
+
+    #[test]
+    fn layout_maps_units_to_cells() {
+        let prefs = Prefs {
+            atlas_size_limit: 4,
+            ..defaults()
+        };
+        let layout = layout(&pack(vec![&RGBY, &C1X1], &prefs)[0], &prefs);
+        assert_eq!((layout.columns, layout.rows), (3, 2));
+        assert_eq!(layout.cells.len(), 5);
+        let mut cells = layout.cells.values().copied().collect::<Vec<_>>();
+        cells.sort_unstable();
+        assert_eq!(cells, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn layout_reports_occupancy() {
+        let prefs = Prefs {
+            atlas_size_limit: 4,
+            ..defaults()
+        };
+        let layout = layout(&pack(vec![&RGBY, &C1X1], &prefs)[0], &prefs);
+        assert!((layout.occupancy() - 5.0 / 6.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn layout_occupancy_includes_spanning_units() {
+        let prefs = Prefs {
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let layout = layout(&pack(vec![&PLT3X3, &RGB4X4], &prefs)[0], &prefs);
+        assert_eq!((layout.columns, layout.rows), (4, 3));
+        assert!((layout.occupancy() - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn units_are_distributed_evenly_when_balanced() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_balance: true,
+            ..defaults()
+        };
+        let src: Vec<&dyn AnySource> = vec![&R1X1, &G1X1, &B1X1, &C1X1, &M1X1, &Y1X1];
+        let atlases = pack(src, &prefs);
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(atlases[0].cells.len(), 3);
+        assert_eq!(atlases[1].cells.len(), 3);
+    }
+
+    #[test]
+    fn balanced_packing_doesnt_add_atlases() {
+        let prefs = Prefs {
+            atlas_size_limit: 3,
+            atlas_balance: true,
+            ..defaults()
+        };
+        let src: Vec<&dyn AnySource> = vec![&PLT3X3, &RGBY, &C1X1, &M1X1];
+        let balanced = pack(src.clone(), &prefs);
+        let greedy = pack(
+            src,
+            &Prefs {
+                atlas_balance: false,
+                ..prefs
+            },
+        );
+        assert_eq!(greedy.len(), 2);
+        assert_eq!(balanced.len(), greedy.len());
+    }
+
+    #[test]
+    fn balanced_packing_with_spanning_units_doesnt_add_atlases() {
+        let prefs = Prefs {
+            atlas_size_limit: 3,
+            atlas_balance: true,
+            reuse_threshold: 1.0,
+            ..defaults()
+        };
+        let sizes = [(2, 3), (3, 1), (1, 3)];
+        let sprites = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, (w, h))| distinct(*w, *h, i as u8 * 10));
+        let diced = crate::dicer::dice(&sprites.collect::<Vec<_>>(), &prefs).unwrap();
+        assert!(diced.iter().all(|t| t.whole));
+        let balanced = crate::packer::pack(diced.clone(), &prefs).unwrap();
+        let greedy = Prefs {
+            atlas_balance: false,
+            ..prefs
+        };
+        let greedy = crate::packer::pack(diced, &greedy).unwrap();
+        assert_eq!(greedy.len(), 2);
+        assert_eq!(balanced.len(), greedy.len());
+    }
+
+    #[test]
+    fn layout_cells_are_remapped_when_uniform() {
+        let prefs = Prefs {
+            atlas_size_limit: 3,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let atlases = pack(vec![&RGB4X4, &PLT3X3], &prefs);
+        assert_eq!(atlases.len(), 2);
+        for atlas in atlases.iter() {
+            let layout = layout(atlas, &prefs);
+            assert_eq!((layout.columns, layout.rows), (3, 3));
+            for (hash, cell) in layout.cells {
+                let rect = &atlas.rects[&hash];
+                assert_eq!((rect.x * 3.0).round() as u32, cell % layout.columns);
+                assert_eq!((rect.y * 3.0).round() as u32, cell / layout.columns);
+            }
+        }
+    }
+
+    #[test]
+    fn appended_units_are_written_into_free_cells() {
+        let prefs = Prefs {
+            atlas_pot: true,
+            ..defaults()
+        };
+        let (previous, (atlases, changed)) = append(vec![&RGBY, &C1X1], vec![&M1X1], &prefs);
+        assert_eq!(atlases.len(), 1);
+        assert_eq!(changed, vec![0]);
+        assert_eq!(atlases[0].cells.len(), 6);
+        assert_eq!(
+            atlases[0]
+                .texture
+                .pixels
+                .iter()
+                .filter(|p| **p == M)
+                .count(),
+            1
+        );
+        for (hash, cell) in previous.layouts[0].cells.iter() {
+            assert_eq!(atlases[0].cells[hash], *cell);
+        }
+    }
+
+    #[test]
+    fn appending_into_uniform_atlases_keeps_previous_layouts() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let previous = crate::dice(&[RGBY.sprite()], &prefs).unwrap();
+        let sprites = [RGBY.sprite(), CMYT.sprite()];
+        let appended = crate::append(&previous, &sprites, &prefs).unwrap();
+        let artifacts = appended.artifacts;
+        assert_eq!(appended.changed, vec![1]);
+        assert_eq!(artifacts.layouts[0], previous.layouts[0]);
+        assert_eq!(artifacts.atlases[0].pixels, previous.atlases[0].pixels);
+        assert_eq!(artifacts.sprites[0].uvs, previous.sprites[0].uvs);
+        let (width, height) = (artifacts.layouts[1].width, artifacts.layouts[1].height);
+        assert_eq!((width, height), (2, 2));
+    }
+
+    #[test]
+    fn errs_when_appended_atlas_exceeds_uniform_atlases() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let previous = crate::dice(&[C1X1.sprite()], &prefs).unwrap();
+        let result = crate::append(&previous, &[RGBY.sprite()], &prefs);
+        assert!(
+            result.is_err_and(|e| e.to_string()
+                == "New atlas exceeds dimensions of the previous uniform atlases.")
+        );
+    }
+
+    #[test]
+    fn patched_uniform_atlases_match_shipped_dimensions() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let shipped = crate::dice(&[RGBY.sprite()], &prefs).unwrap();
+        let patched = crate::patch(&shipped.layouts, &[C1X1.sprite()], &prefs).unwrap();
+        assert_eq!(patched.layouts.len(), 1);
+        assert_eq!(
+            (patched.layouts[0].width, patched.layouts[0].height),
+            (2, 2)
+        );
+        assert_eq!(patched.atlases[0].width * patched.atlases[0].height, 4);
+    }
+
+    #[test]
+    fn errs_when_patched_atlas_exceeds_uniform_atlases() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            atlas_uniform: true,
+            ..defaults()
+        };
+        let shipped = crate::dice(&[C1X1.sprite()], &prefs).unwrap();
+        let result = crate::patch(&shipped.layouts, &[RGBY.sprite()], &prefs);
+        assert!(
+            result.is_err_and(|e| e.to_string()
+                == "New atlas exceeds dimensions of the previous uniform atlases.")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Appended atlas has no free cells left.")]
+    fn errs_when_appending_into_full_atlas() {
+        let prefs = defaults();
+        let mut atlas = pack(vec![&RGBY], &prefs).pop().unwrap();
+        let texture = crate::dicer::dice(&[C1X1.sprite()], &prefs).unwrap().pop();
+        let ctx = new_ctx(vec![], &prefs);
+        append_texture(&ctx, &mut atlas, texture.unwrap()).unwrap();
+    }
+
+    #[test]
+    fn appended_units_already_packed_are_reused() {
+        let (_, (atlases, changed)) = append(vec![&RGBY], vec![&B1X1], &defaults());
+        assert_eq!(atlases.len(), 1);
+        assert!(changed.is_empty());
+        assert_eq!(atlases[0].cells.len(), 4);
+        assert_eq!(atlases[0].packed.len(), 1);
+    }
+
+    #[test]
+    fn when_appended_units_dont_fit_new_atlas_is_added() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            ..defaults()
+        };
+        let (previous, (atlases, changed)) = append(vec![&RGBY], vec![&C1X1], &prefs);
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(changed, vec![1]);
+        assert_eq!(atlases[0].texture.pixels, previous.atlases[0].pixels);
+        assert!(atlases[0].packed.is_empty());
+    }
+
+    #[test]
+    fn appended_units_are_not_packed_into_atlases_of_other_groups() {
+        let prefs = Prefs {
+            atlas_pot: true,
+            ..defaults()
+        };
+        let (_, (atlases, changed)) = append(vec![&RGBY], vec![&(&C1X1, "a")], &prefs);
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(changed, vec![1]);
+        assert_eq!(atlases[1].group.as_deref(), Some("a"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Previous atlases don't match the layouts.")]
+    fn errs_when_appending_without_layouts() {
+        let previous = Artifacts {
+            atlases: vec![R1X1.to_owned()],
+            layouts: vec![],
+            sprites: vec![],
+            aliases: vec![],
+        };
+        let diced = crate::dicer::dice(&[B1X1.sprite()], &defaults()).unwrap();
+        crate::packer::append(diced, &previous, &defaults()).unwrap();
+    }
+
+    #[test]
+    fn patched_atlases_contain_only_new_units() {
+        let atlases = patch(vec![&RGBY], vec![&RGBY, &C1X1], &defaults());
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(atlases[1].cells.len(), 1);
+        assert_eq!(atlases[0].packed.len(), 1);
+        assert_eq!(atlases[1].packed.len(), 1);
+    }
+
+    #[test]
+    fn patched_units_shipped_are_referenced() {
+        let atlases = patch(vec![&R1X1], vec![&RGBY], &defaults());
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(atlases[0].rects.len(), 1);
+        assert_eq!(atlases[1].cells.len(), 3);
+        assert!(atlases[0].texture.pixels.is_empty());
+    }
+
+    #[test]
+    fn patched_units_are_not_referenced_from_other_groups() {
+        let atlases = patch(vec![&RGBY], vec![&(&R1X1, "a")], &defaults());
+        assert_eq!(atlases.len(), 2);
+        assert!(atlases[0].rects.is_empty());
+        assert_eq!(atlases[1].group.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn packing_matches_exhaustive_scan() {
+        let prefs = Prefs {
+            unit_size: 2,
+            atlas_size_limit: 12,
+            ..defaults()
+        };
+        let sprites = (0..300).map(random_sprite).collect::<Vec<_>>();
+        let diced = crate::dicer::dice(&sprites, &prefs).unwrap();
+        let expected = scan(&diced, 36);
+        let atlases = crate::packer::pack(diced, &prefs).unwrap();
+        assert!(atlases.len() > 2);
+        for (atlas, expected) in atlases.iter().zip(expected) {
+            let mut ids = atlas
+                .packed
+                .iter()
+                .map(|t| t.id.to_owned())
+                .collect::<Vec<_>>();
+            ids.sort();
+            assert_eq!(ids, expected);
+        }
+    }
+
+    #[test]
+    fn whole_units_span_multiple_cells() {
+        let prefs = Prefs {
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let atlas = &pack(vec![&PLT3X3], &prefs)[0];
+        assert_eq!((atlas.texture.width, atlas.texture.height), (3, 3));
+        assert_eq!(atlas.cells.values().copied().collect::<Vec<_>>(), vec![0]);
+        assert_eq!(
+            atlas.spans.values().collect::<Vec<_>>(),
+            vec![&USize::new(3, 3)]
+        );
+    }
+
+    #[test]
+    fn single_cell_units_fill_cells_around_spanning_units() {
+        let prefs = Prefs {
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let atlas = &pack(vec![&PLT3X3, &RGB4X4], &prefs)[0];
+        assert_eq!((atlas.texture.width, atlas.texture.height), (4, 3));
+        let mut cells = atlas.cells.values().copied().collect::<Vec<_>>();
+        cells.sort_unstable();
+        assert_eq!(cells, vec![0, 3, 7, 11]);
+    }
+
+    #[test]
+    fn whole_textures_not_fitting_are_moved_to_next_atlas() {
+        let prefs = Prefs {
+            atlas_size_limit: 3,
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let atlases = pack(vec![&RGBY, &CMYT], &prefs);
+        assert_eq!(atlases.len(), 2);
+        assert!(
+            atlases
+                .iter()
+                .all(|a| a.packed.len() == 1 && a.spans.len() == 1)
+        );
+    }
+
+    #[test]
+    fn whole_textures_are_evicted_from_each_atlas() {
+        let prefs = Prefs {
+            atlas_size_limit: 4,
+            reuse_threshold: 1.0,
+            ..defaults()
+        };
+        let sizes = [(3, 2), (1, 3), (2, 3), (1, 3)];
+        let sprites = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, (w, h))| distinct(*w, *h, i as u8 * 10));
+        let diced = crate::dicer::dice(&sprites.collect::<Vec<_>>(), &prefs).unwrap();
+        assert!(diced.iter().all(|t| t.whole));
+        assert!(crate::packer::pack(diced, &prefs).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't fit single texture; increase atlas size limit.")]
+    fn errs_when_whole_texture_doesnt_fit() {
+        let prefs = Prefs {
+            atlas_size_limit: 2,
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        pack(vec![&RGB1X3], &prefs);
+    }
+
+    #[test]
+    fn layout_maps_spans() {
+        let prefs = Prefs {
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let layout = layout(&pack(vec![&PLT3X3], &prefs)[0], &prefs);
+        assert_eq!(layout.spans.values().collect::<Vec<_>>(), vec![&(3, 3)]);
+    }
+
+    #[test]
+    fn appended_whole_units_are_not_written_into_free_cells() {
+        let prefs = Prefs {
+            atlas_pot: true,
+            reuse_threshold: 0.5,
+            ..defaults()
+        };
+        let src: Vec<&dyn AnySource> = vec![&RGB4X4, &RGBY, &CMYT, &C1X1, &M1X1];
+        let (previous, (atlases, changed)) = append(src, vec![&PLT3X3], &prefs);
+        let total = previous.layouts[0].columns * previous.layouts[0].rows;
+        assert!(total - previous.layouts[0].cells.len() as u32 >= 9);
+        assert_eq!(atlases.len(), 2);
+        assert_eq!(changed, vec![1]);
+    }
+
     #[test]
     fn reports_progress() {
         let progress = sample_progress(|p| drop(pack(vec![&M1X1], &p)));
         assert_eq!(progress.ratio, 0.6);
     }
 
+    /// Creates source sprite with opaque pixels of distinct colors, starting with specified
+    /// red component, so that the sprite has no units in common with the other sprites.
+    fn distinct(width: u32, height: u32, from: u8) -> SourceSprite {
+        let pixels = (0..width * height).map(|i| Pixel::new(from + i as u8, 0, 0, 255));
+        SourceSprite {
+            id: from.to_string(),
+            texture: Texture {
+                width,
+                height,
+                pixels: pixels.collect(),
+            },
+            pivot: None,
+            origin: None,
+            group: None,
+            ppu: None,
+            border: None,
+        }
+    }
+
     fn pack(src: Vec<&dyn AnySource>, prefs: &Prefs) -> Vec<Atlas> {
         let sprites = src.into_iter().map(|s| s.sprite()).collect::<Vec<_>>();
         let diced = crate::dicer::dice(&sprites, prefs).unwrap();
         crate::packer::pack(diced, prefs).unwrap()
     }
 
+    fn append(
+        src: Vec<&dyn AnySource>,
+        new: Vec<&dyn AnySource>,
+        prefs: &Prefs,
+    ) -> (Artifacts, (Vec<Atlas>, Vec<usize>)) {
+        let atlases = pack(src, prefs);
+        let previous = Artifacts {
+            layouts: atlases.iter().map(|a| layout(a, prefs)).collect(),
+            atlases: atlases.into_iter().map(|a| a.texture).collect(),
+            sprites: vec![],
+            aliases: vec![],
+        };
+        let sprites = new.into_iter().map(|s| s.sprite()).collect::<Vec<_>>();
+        let diced = crate::dicer::dice(&sprites, prefs).unwrap();
+        let appended = crate::packer::append(diced, &previous, prefs).unwrap();
+        (previous, appended)
+    }
+
+    fn patch(shipped: Vec<&dyn AnySource>, new: Vec<&dyn AnySource>, prefs: &Prefs) -> Vec<Atlas> {
+        let atlases = pack(shipped, prefs);
+        let layouts = atlases.iter().map(|a| layout(a, prefs)).collect::<Vec<_>>();
+        let sprites = new.into_iter().map(|s| s.sprite()).collect::<Vec<_>>();
+        let diced = crate::dicer::dice(&sprites, prefs).unwrap();
+        crate::packer::patch(diced, &layouts, prefs).unwrap()
+    }
+
+    /// Generates 4x4 sprite with pseudo-random pixels of 3 colors.
+    fn random_sprite(seed: usize) -> SourceSprite {
+        let mut state = seed as u32 * 7919 + 1;
+        let pixels = (0..16).map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            [R, G, B][(state >> 16) as usize % 3]
+        });
+        SourceSprite {
+            id: format!("{seed:03}"),
+            texture: Texture {
+                width: 4,
+                height: 4,
+                pixels: pixels.collect(),
+            },
+            pivot: None,
+            origin: None,
+            group: None,
+            ppu: None,
+            border: None,
+        }
+    }
+
+    /// Reference texture selection re-scanning all the remaining textures on each insertion;
+    /// returns sorted IDs of the textures packed into each atlas.
+    fn scan(diced: &[DicedTexture], capacity: usize) -> Vec<Vec<String>> {
+        let to_pack = diced.iter().map(|t| (t.id.to_owned(), &t.unique));
+        let mut to_pack = to_pack.collect::<Vec<_>>();
+        let mut atlases = vec![];
+        while !to_pack.is_empty() {
+            let mut packed = HashSet::new();
+            let mut units = HashSet::new();
+            loop {
+                let candidates = to_pack
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !packed.contains(i));
+                let missing = candidates.map(|(i, (_, u))| (u.difference(&units).count(), i));
+                match missing.min() {
+                    Some((count, idx)) if units.len() + count <= capacity => {
+                        packed.insert(idx);
+                        units.extend(to_pack[idx].1.iter().copied());
+                    }
+                    _ => break,
+                }
+            }
+            let mut ids = vec![];
+            for idx in (0..to_pack.len()).rev() {
+                if packed.contains(&idx) {
+                    ids.push(to_pack.swap_remove(idx).0);
+                }
+            }
+            ids.sort();
+            atlases.push(ids);
+        }
+        atlases
+    }
+
     fn defaults() -> Prefs {
         Prefs {
             unit_size: 1,
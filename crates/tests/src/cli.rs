@@ -4,9 +4,12 @@ use crate::common::*;
 use cli::models::*;
 use rand::{Rng, distr::Alphanumeric};
 use serde_json::Value;
-use sprite_dicing::{Artifacts, DicedSprite, Pivot, Prefs, Rect, Uv, Vertex};
+use sprite_dicing::{
+    Alias, Artifacts, Border, DicedSprite, Grid, Instance, Lod, Pivot, Prefs, Quantized, Rect,
+    SourceSprite, Uv, Vertex,
+};
 use std::path::{Path, PathBuf};
-use std::{fs, str::FromStr, vec};
+use std::{fs, vec};
 
 #[test]
 fn mono_reproduced() {
@@ -27,6 +30,120 @@ fn mono_reproduced() {
     fs::remove_dir_all(out_dir).unwrap();
 }
 
+#[test]
+fn quantized_reproduced() {
+    let out_dir = create_temp_dir();
+
+    let prefs = Prefs {
+        unit_size: 2,
+        padding: 0,
+        quantize: true,
+        ..Prefs::default()
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&DIR[MONO], &fs_prefs, &prefs).unwrap();
+    let mut arts = build_arts(&out_dir, &fs_prefs);
+    for sprite in arts.sprites.iter_mut() {
+        let quantized = sprite.quantized.take().unwrap();
+        let origin = quantized.origin;
+        let vertices = quantized.vertices.iter().map(|[x, y]| Vertex {
+            x: (*x as f32 - origin.x) / prefs.ppu,
+            y: (*y as f32 - origin.y) / prefs.ppu,
+        });
+        sprite.vertices = vertices.collect();
+        let uvs = quantized.uvs.iter().map(|[u, v]| Uv {
+            u: *u as f32 / u16::MAX as f32,
+            v: *v as f32 / u16::MAX as f32,
+        });
+        sprite.uvs = uvs.collect();
+    }
+    assert_repro(MONO, arts, &prefs);
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn outline_serialized() {
+    let out_dir = create_temp_dir();
+
+    let prefs = Prefs {
+        build_outline: true,
+        ..Prefs::default()
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&DIR[ICONS], &fs_prefs, &prefs).unwrap();
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    for sprite in build_arts(&out_dir, &fs_prefs).sprites {
+        let source = diced.sprites.iter().find(|s| s.id == sprite.id).unwrap();
+        assert!(!sprite.outline.is_empty());
+        assert_eq!(sprite.outline, source.outline);
+    }
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn border_serialized() {
+    let out_dir = create_temp_dir();
+
+    let prefs = Prefs::default();
+    let border = Border {
+        left: 4,
+        top: 4,
+        right: 4,
+        bottom: 4,
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        border: Some(border.to_owned()),
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&DIR[ICONS], &fs_prefs, &prefs).unwrap();
+    let sources = SRC[ICONS].iter().map(|s| SourceSprite {
+        border: Some(border.to_owned()),
+        ..s.to_owned()
+    });
+    let diced = sprite_dicing::dice(&sources.collect::<Vec<_>>(), &prefs).unwrap();
+    for sprite in build_arts(&out_dir, &fs_prefs).sprites {
+        let source = diced.sprites.iter().find(|s| s.id == sprite.id).unwrap();
+        assert_eq!(sprite.border, Some(border.to_owned()));
+        assert!(!sprite.slices.is_empty());
+        assert_eq!(sprite.slices, source.slices);
+    }
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn indirection_written() {
+    let out_dir = create_temp_dir();
+
+    let prefs = Prefs {
+        build_indirection: true,
+        ..Prefs::default()
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&DIR[ICONS], &fs_prefs, &prefs).unwrap();
+    let json = fs::read_to_string(out_dir.join("sprites.json")).unwrap();
+    let json = serde_json::from_str::<Value>(&json).unwrap();
+    for (idx, sprite) in json.as_array().unwrap().iter().enumerate() {
+        let name = format!("indirection_{idx}.png");
+        assert_eq!(sprite["indirection"].as_str(), Some(name.as_str()));
+        assert!(out_dir.join(name).exists());
+    }
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
 #[test]
 fn nested_reproduced() {
     let out_dir = create_temp_dir();
@@ -47,6 +164,80 @@ fn nested_reproduced() {
     fs::remove_dir_all(out_dir).unwrap();
 }
 
+#[test]
+fn nested_grouped_by_directories() {
+    let out_dir = create_temp_dir();
+
+    let prefs = Prefs {
+        unit_size: 1,
+        padding: 0,
+        ..Prefs::default()
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        recursive: true,
+        group: true,
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&DIR[NESTED], &fs_prefs, &prefs).unwrap();
+    let sprites = build_arts(&out_dir, &fs_prefs).sprites;
+    for sprite in &sprites {
+        let group = sprite.id.rsplit_once('/').map(|(dir, _)| dir);
+        assert_eq!(sprite.group.as_deref(), group);
+    }
+    for group in [None, Some("rgb"), Some("cmy")] {
+        let grouped = sprites.iter().filter(|s| s.group.as_deref() == group);
+        let atlas = grouped.map(|s| s.atlas_index).collect::<Vec<_>>();
+        assert!(atlas.windows(2).all(|w| w[0] == w[1]));
+        assert!(
+            sprites
+                .iter()
+                .all(|s| s.group.as_deref() == group || s.atlas_index != atlas[0])
+        );
+    }
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn can_write_atlas_array() {
+    let out_dir = create_temp_dir();
+
+    let prefs = Prefs {
+        unit_size: 1,
+        padding: 0,
+        atlas_size_limit: 2,
+        atlas_uniform: true,
+        ..Prefs::default()
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        atlas_array: true,
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&DIR[MONO], &fs_prefs, &prefs).unwrap();
+    let array = image::open(out_dir.join("atlas_array_0.png")).unwrap();
+    assert!(!out_dir.join("atlas_0.png").exists());
+    assert_eq!(array.width(), 2);
+    assert_eq!(array.height(), 4);
+    let json = fs::read_to_string(out_dir.join("sprites.json")).unwrap();
+    let json = serde_json::from_str::<Value>(&json).unwrap();
+    let sprites = json.as_array().unwrap();
+    assert!(sprites.iter().all(|s| s["atlas"].as_u64() == Some(0)));
+    assert!(sprites.iter().any(|s| s["layer"].as_u64() == Some(1)));
+    let json = fs::read_to_string(out_dir.join("atlases.json")).unwrap();
+    let json = serde_json::from_str::<Value>(&json).unwrap();
+    let atlases = json.as_array().unwrap();
+    assert_eq!(atlases.len(), 2);
+    assert!(
+        atlases
+            .iter()
+            .all(|a| a["occupancy"].as_f64().unwrap() > 0.0)
+    );
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
 #[test]
 fn exotic_reproduced() {
     let out_dir = create_temp_dir();
@@ -79,6 +270,77 @@ fn errs_on_invalid_source() {
     fs::remove_dir_all(out_dir).unwrap();
 }
 
+#[test]
+fn errs_on_duplicate_ids() {
+    let src_dir = create_temp_dir();
+    let png = fs::read_dir(&DIR[MONO])
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    fs::copy(&png, src_dir.join("img@100.png")).unwrap();
+    fs::copy(&png, src_dir.join("img@200.png")).unwrap();
+    let fs_prefs = FsPrefs {
+        out: Some(src_dir.to_owned()),
+        ppu_suffix: true,
+        ..FsPrefs::default()
+    };
+    assert!(
+        cli::dice_dir(&src_dir, &fs_prefs, &Prefs::default())
+            .is_err_and(|e| e.to_string().contains("sprite ID 'img'"))
+    );
+    fs::remove_dir_all(src_dir).unwrap();
+}
+
+#[test]
+fn duplicates_written_as_aliases() {
+    let src_dir = create_temp_dir();
+    let out_dir = create_temp_dir();
+    for entry in fs::read_dir(&DIR[MONO]).unwrap() {
+        let path = entry.unwrap().path();
+        let stem = path.file_stem().unwrap().to_str().unwrap();
+        fs::copy(&path, src_dir.join(format!("{stem}.png"))).unwrap();
+        fs::copy(&path, src_dir.join(format!("{stem}_copy.png"))).unwrap();
+    }
+
+    let prefs = Prefs {
+        unit_size: 1,
+        padding: 0,
+        alias_duplicates: true,
+        ..Prefs::default()
+    };
+    let fs_prefs = FsPrefs {
+        out: Some(out_dir.to_owned()),
+        ..FsPrefs::default()
+    };
+
+    cli::dice_dir(&src_dir, &fs_prefs, &prefs).unwrap();
+    let arts = build_arts(&out_dir, &fs_prefs);
+    assert_eq!(arts.aliases.len(), arts.sprites.len());
+    for alias in arts.aliases.iter() {
+        // Either the original or the copy is diced first, depending on directory order.
+        let original = |id: &str| id.trim_end_matches("_copy").to_owned();
+        assert_ne!(alias.id, alias.target);
+        assert_eq!(original(&alias.id), original(&alias.target));
+        assert!(arts.sprites.iter().any(|s| s.id == alias.target));
+        assert!(arts.sprites.iter().all(|s| s.id != alias.id));
+    }
+    // Resolve the aliases to reproduce the originals, regardless of which was aliased.
+    let mut sprites = arts.sprites.to_owned();
+    for alias in arts.aliases.iter() {
+        let target = arts.sprites.iter().find(|s| s.id == alias.target).unwrap();
+        sprites.push(DicedSprite {
+            id: alias.id.to_owned(),
+            ..target.to_owned()
+        });
+    }
+    sprites.retain(|s| !s.id.ends_with("_copy"));
+    assert_repro(MONO, Artifacts { sprites, ..arts }, &prefs);
+    fs::remove_dir_all(src_dir).unwrap();
+    fs::remove_dir_all(out_dir).unwrap();
+}
+
 #[test]
 fn can_write_webp() {
     let out_dir = create_temp_dir();
@@ -135,44 +397,167 @@ fn build_arts(dir: &Path, prefs: &FsPrefs) -> Artifacts {
         .map(parse_diced_sprite)
         .collect();
 
-    Artifacts { atlases, sprites }
+    let path = dir.join("aliases.json");
+    let aliases = match path.exists() {
+        true => parse_aliases(&fs::read_to_string(path).unwrap()),
+        false => vec![],
+    };
+
+    Artifacts {
+        atlases,
+        layouts: vec![],
+        sprites,
+        aliases,
+    }
+}
+
+fn parse_aliases(json: &str) -> Vec<Alias> {
+    let json = serde_json::from_str::<Value>(json).unwrap();
+    let aliases = json.as_array().unwrap().iter().map(|a| Alias {
+        id: a["id"].as_str().unwrap().to_owned(),
+        target: a["target"].as_str().unwrap().to_owned(),
+    });
+    aliases.collect()
 }
 
 fn parse_diced_sprite(json: &Value) -> DicedSprite {
     DicedSprite {
         id: json["id"].as_str().unwrap().to_owned(),
         atlas_index: json["atlas"].as_u64().unwrap() as usize,
+        atlas_indices: parse_indices(&json["atlas_indices"]),
+        layer: json["layer"].as_u64().unwrap() as usize,
         vertices: json["vertices"]
             .as_array()
             .unwrap()
             .iter()
-            .map(|v| Vertex {
-                x: v["x"].as_f64().unwrap() as f32,
-                y: v["y"].as_f64().unwrap() as f32,
-            })
+            .map(parse_vertex)
             .collect::<Vec<_>>(),
         uvs: json["uvs"]
             .as_array()
             .unwrap()
             .iter()
-            .map(|v| Uv {
-                u: v["u"].as_f64().unwrap() as f32,
-                v: v["v"].as_f64().unwrap() as f32,
-            })
+            .map(parse_uv)
             .collect::<Vec<_>>(),
         indices: json["indices"]
             .as_array()
             .unwrap()
             .iter()
             .map(|v| v.as_u64().unwrap() as usize)
-            .collect::<Vec<_>>(),
-        rect: Rect {
-            x: json["rect"]["x"].as_f64().unwrap() as f32,
-            y: json["rect"]["y"].as_f64().unwrap() as f32,
-            width: json["rect"]["width"].as_f64().unwrap() as f32,
-            height: json["rect"]["height"].as_f64().unwrap() as f32,
-        },
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap(),
+        rect: parse_rect(&json["rect"]),
         pivot: Pivot::new(0.5, 0.5),
+        group: json["group"].as_str().map(str::to_owned),
+        whole: json["whole"].as_bool().unwrap(),
+        indirection: None,
+        grid: json["grid"].as_object().map(|g| Grid {
+            columns: g["columns"].as_u64().unwrap() as u32,
+            rows: g["rows"].as_u64().unwrap() as u32,
+            cells: g["cells"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c.as_u64().map(|c| c as u32))
+                .collect(),
+        }),
+        instances: json["instances"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|i| Instance {
+                rect: parse_rect(i),
+                uv: i["uv"].as_u64().unwrap() as usize,
+            })
+            .collect(),
+        instance_uvs: json["instance_uvs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(parse_rect)
+            .collect(),
+        quantized: json["quantized"].as_object().map(|q| Quantized {
+            origin: Vertex {
+                x: q["origin"]["x"].as_f64().unwrap() as f32,
+                y: q["origin"]["y"].as_f64().unwrap() as f32,
+            },
+            vertices: parse_pairs(&q["vertices"], |n| n.as_i64().unwrap() as i16),
+            uvs: parse_pairs(&q["uvs"], |n| n.as_u64().unwrap() as u16),
+        }),
+        outline: json["outline"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p.as_array().unwrap().iter().map(parse_vertex).collect())
+            .collect(),
+        border: json["border"].as_object().map(|b| Border {
+            left: b["left"].as_u64().unwrap() as u32,
+            top: b["top"].as_u64().unwrap() as u32,
+            right: b["right"].as_u64().unwrap() as u32,
+            bottom: b["bottom"].as_u64().unwrap() as u32,
+        }),
+        slices: json["slices"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s.as_u64().unwrap() as u8)
+            .collect(),
+        lods: json["lods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| Lod {
+                atlas_indices: parse_indices(&l["atlas_indices"]),
+                vertices: l["vertices"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(parse_vertex)
+                    .collect(),
+                uvs: l["uvs"].as_array().unwrap().iter().map(parse_uv).collect(),
+                indices: l["indices"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|v| v.as_u64().unwrap() as usize)
+                    .collect::<Vec<_>>()
+                    .try_into()
+                    .unwrap(),
+            })
+            .collect(),
+    }
+}
+
+fn parse_indices(json: &Value) -> Vec<usize> {
+    let indices = json.as_array().unwrap().iter();
+    indices.map(|i| i.as_u64().unwrap() as usize).collect()
+}
+
+fn parse_vertex(json: &Value) -> Vertex {
+    Vertex {
+        x: json["x"].as_f64().unwrap() as f32,
+        y: json["y"].as_f64().unwrap() as f32,
+    }
+}
+
+fn parse_uv(json: &Value) -> Uv {
+    Uv {
+        u: json["u"].as_f64().unwrap() as f32,
+        v: json["v"].as_f64().unwrap() as f32,
+    }
+}
+
+fn parse_pairs<T>(json: &Value, parse: impl Fn(&Value) -> T) -> Vec<[T; 2]> {
+    let pairs = json.as_array().unwrap().iter();
+    pairs.map(|p| [parse(&p[0]), parse(&p[1])]).collect()
+}
+
+fn parse_rect(json: &Value) -> Rect {
+    Rect {
+        x: json["x"].as_f64().unwrap() as f32,
+        y: json["y"].as_f64().unwrap() as f32,
+        width: json["width"].as_f64().unwrap() as f32,
+        height: json["height"].as_f64().unwrap() as f32,
     }
 }
 
@@ -182,8 +567,7 @@ fn create_temp_dir() -> PathBuf {
         .take(8)
         .map(char::from)
         .collect();
-    let crate_dir = env!("CARGO_MANIFEST_DIR");
-    let tmp_dir = PathBuf::from_str(&format!("{crate_dir}/tmp-{rand}")).unwrap();
+    let tmp_dir = std::env::temp_dir().join(format!("sprite-dicing-{rand}"));
     _ = fs::remove_dir_all(&tmp_dir);
     fs::create_dir(&tmp_dir).unwrap();
     tmp_dir
@@ -16,17 +16,20 @@ pub fn assert_repro(fixture: &str, arts: Artifacts, prefs: &Prefs) {
             continue;
         }
         let diced = arts.sprites.iter().find(|&d| d.id == source.id).unwrap();
-        let atlas = &atlases[diced.atlas_index];
-        let reproduced = &reproduce(diced, atlas, prefs);
+        let reproduced = &reproduce(diced, &atlases, prefs);
         assert_eq!(source_raw, reproduced);
     }
 }
 
-fn reproduce(diced: &DicedSprite, atlas: &RgbaImage, prefs: &Prefs) -> RgbaImage {
+fn reproduce(diced: &DicedSprite, atlases: &[RgbaImage], prefs: &Prefs) -> RgbaImage {
     let sprite_width = (diced.rect.width * prefs.ppu) as u32;
     let sprite_height = (diced.rect.height * prefs.ppu) as u32;
     let mut img = ImageBuffer::new(sprite_width, sprite_height);
     for (idx, _) in diced.vertices.iter().enumerate().step_by(4) {
+        // Each quad is formed by 2 faces (triangles) sampling the same atlas.
+        let atlas_idx = diced.atlas_indices.get(idx / 2);
+        let atlas = &atlases[*atlas_idx.unwrap_or(&diced.atlas_index)];
+
         // Vertices layout by index:
         // min -> [0] [3]
         //        [1] [2] <- max
@@ -73,3 +76,33 @@ fn lerp(a: f32, b: f32, w: f32) -> f32 {
     };
     a + w * (b - a)
 }
+
+/// Asserts source sprites under specified fixture can be reproduced using the unit grids.
+pub fn assert_grid_repro(fixture: &str, arts: Artifacts, prefs: &Prefs) {
+    let padded_unit_size = prefs.unit_size + prefs.padding * 2;
+    for source in SRC[fixture].iter() {
+        let Some(diced) = arts.sprites.iter().find(|&d| d.id == source.id) else {
+            continue;
+        };
+        let grid = diced.grid.as_ref().unwrap();
+        let atlas = &arts.atlases[diced.atlas_index];
+        let columns = atlas.width / padded_unit_size;
+        let src = &source.texture;
+        for y in 0..src.height {
+            for x in 0..src.width {
+                let src_pixel = src.pixels[(y * src.width + x) as usize];
+                let unit = (y / prefs.unit_size) * grid.columns + x / prefs.unit_size;
+                let Some(cell) = grid.cells[unit as usize] else {
+                    assert_eq!(src_pixel.a(), 0);
+                    continue;
+                };
+                let atlas_x = (cell % columns) * padded_unit_size + prefs.padding;
+                let atlas_y = (cell / columns) * padded_unit_size + prefs.padding;
+                let atlas_x = atlas_x + x % prefs.unit_size;
+                let atlas_y = atlas_y + y % prefs.unit_size;
+                let atlas_pixel = atlas.pixels[(atlas_y * atlas.width + atlas_x) as usize];
+                assert_eq!(src_pixel, atlas_pixel);
+            }
+        }
+    }
+}
@@ -66,6 +66,10 @@ fn create_sprite(path: &Path, image: RgbaImage, root: &Path) -> SourceSprite {
         id: build_id(path, root),
         texture: img::to_texture(&image),
         pivot: None,
+        origin: None,
+        group: None,
+        ppu: None,
+        border: None,
     }
 }
 
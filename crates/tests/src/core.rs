@@ -1,7 +1,9 @@
 //! End-to-end tests of the core library.
 
 use crate::common::*;
-use sprite_dicing::Prefs;
+use sprite_dicing::{
+    Anchor, Artifacts, Border, Error, Library, Origin, Pivot, Prefs, SourceSprite, Uv, Vertex,
+};
 
 #[test]
 fn mono_1x_reproduced() {
@@ -94,6 +96,281 @@ fn icons_reproduced() {
     assert_repro(ICONS, diced, &prefs);
 }
 
+#[test]
+fn streamed_reproduced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        ..Prefs::default()
+    };
+    let sources = SRC[ICONS].iter().cloned().map(Ok::<_, Error>);
+    let diced = sprite_dicing::dice_stream(sources, &prefs).unwrap();
+    assert_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn streamed_error_returned() {
+    let sources = [
+        Ok(SRC[ICONS][0].clone()),
+        Err(Error::Spec("Failed to decode.")),
+    ];
+    let result = sprite_dicing::dice_stream(sources, &Prefs::default());
+    assert!(matches!(result, Err(Error::Spec("Failed to decode."))));
+}
+
+#[test]
+fn hybrid_reproduced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        reuse_threshold: 0.5,
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    assert!(diced.sprites.iter().any(|s| s.whole));
+    assert!(diced.sprites.iter().any(|s| !s.whole));
+    assert_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn packed_whole_reproduced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        atlas_pot: true,
+        ..Prefs::default()
+    };
+    let packed = sprite_dicing::pack(&SRC[ICONS], &prefs).unwrap();
+    assert!(
+        packed
+            .sprites
+            .iter()
+            .all(|s| s.whole && s.indices.len() == 6)
+    );
+    assert_repro(ICONS, packed, &prefs);
+}
+
+#[test]
+fn grid_reproduced() {
+    let prefs = Prefs {
+        padding: 1,
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    assert_grid_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn flipped_conventions_mirror_defaults() {
+    let prefs = Prefs {
+        y_up: true,
+        uv_bottom_left: true,
+        clockwise: true,
+        pivot: Pivot::new(0.5, 0.25),
+        ..Prefs::default()
+    };
+    let flipped = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    let prefs = Prefs {
+        pivot: Pivot::new(0.5, 0.75),
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    let pixels = |a: &Artifacts| {
+        a.atlases
+            .iter()
+            .map(|t| t.pixels.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(pixels(&flipped), pixels(&diced));
+    for flipped in flipped.sprites.iter() {
+        let diced = diced.sprites.iter().find(|s| s.id == flipped.id).unwrap();
+        let vertices = diced.vertices.iter().map(|v| Vertex::new(v.x, -v.y));
+        assert!(flipped.vertices.iter().cloned().eq(vertices));
+        let uvs = diced.uvs.iter().map(|uv| Uv::new(uv.u, 1.0 - uv.v));
+        assert!(flipped.uvs.iter().cloned().eq(uvs));
+        let indices: Vec<_> = diced.indices.iter().collect();
+        let faces = indices.chunks(3).flat_map(|f| [f[0], f[2], f[1]]);
+        assert!(flipped.indices.iter().eq(faces));
+        let rect = &diced.rect;
+        assert_eq!(flipped.rect.y, -(rect.y + rect.height));
+    }
+}
+
+#[test]
+fn anchored_origin_unaffected_by_trim() {
+    let prefs = Prefs {
+        origin: Some(Origin::Anchor(Anchor::BottomCenter)),
+        trim_transparent: true,
+        ..Prefs::default()
+    };
+    let trimmed = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    let prefs = Prefs {
+        trim_transparent: false,
+        ..prefs
+    };
+    let full = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    for trimmed in trimmed.sprites.iter() {
+        let full = full.sprites.iter().find(|s| s.id == trimmed.id).unwrap();
+        assert_eq!(trimmed.vertices, full.vertices);
+        assert!(trimmed.rect.width <= full.rect.width);
+        assert_eq!(full.pivot, Pivot::new(0.5, 1.0));
+    }
+}
+
+#[test]
+fn outline_encloses_opaque_pixels() {
+    let prefs = Prefs {
+        build_outline: true,
+        outline_tolerance: 0.0,
+        outline_threshold: 1,
+        trim_transparent: false,
+        pivot: Pivot::new(0.0, 0.0),
+        ppu: 1.0,
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    for source in SRC[ICONS].iter() {
+        let sprite = diced.sprites.iter().find(|s| s.id == source.id).unwrap();
+        let tex = &source.texture;
+        for y in 0..tex.height {
+            let crossings = cross_row(&sprite.outline, y as f32 + 0.5);
+            for x in 0..tex.width {
+                let left = crossings.partition_point(|c| *c < x as f32 + 0.5);
+                let opaque = tex.pixels[(x + y * tex.width) as usize].a() > 0;
+                assert_eq!(left % 2 == 1, opaque);
+            }
+        }
+    }
+}
+
+/// Collects sorted X positions where edges of specified closed paths cross horizontal line.
+fn cross_row(paths: &[Vec<Vertex>], y: f32) -> Vec<f32> {
+    let mut crossings = vec![];
+    for path in paths {
+        for (idx, a) in path.iter().enumerate() {
+            let b = &path[(idx + 1) % path.len()];
+            if (a.y > y) != (b.y > y) {
+                crossings.push(a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x));
+            }
+        }
+    }
+    crossings.sort_by(f32::total_cmp);
+    crossings
+}
+
+#[test]
+fn sliced_reproduced() {
+    let prefs = Prefs {
+        unit_size: 4,
+        padding: 0,
+        ..Prefs::default()
+    };
+    let mut sources = SRC[MONO].to_owned();
+    for source in sources.iter_mut() {
+        let (width, height) = (source.texture.width, source.texture.height);
+        source.border = Some(Border {
+            left: width / 3,
+            top: height / 4,
+            right: width / 5,
+            bottom: height / 6,
+        });
+    }
+    let diced = sprite_dicing::dice(&sources, &prefs).unwrap();
+    assert!(
+        diced
+            .sprites
+            .iter()
+            .all(|s| s.slices.len() == s.vertices.len())
+    );
+    assert!(diced.sprites.iter().all(|s| s.slices.contains(&4)));
+    assert_repro(MONO, diced, &prefs);
+}
+
+#[test]
+fn lods_stay_within_sprite_rect() {
+    let prefs = Prefs {
+        lod_count: 2,
+        ppu: 1.0,
+        trim_transparent: false,
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    for sprite in diced.sprites.iter().filter(|s| !s.whole) {
+        assert_eq!(sprite.lods.len(), 2);
+        assert!(sprite.lods[0].vertices.len() <= sprite.vertices.len());
+        assert!(sprite.lods[1].vertices.len() <= sprite.lods[0].vertices.len());
+        let rect = &sprite.rect;
+        for vertex in sprite.lods.iter().flat_map(|l| l.vertices.iter()) {
+            assert!(vertex.x >= rect.x && vertex.x <= rect.x + rect.width);
+            assert!(vertex.y >= rect.y && vertex.y <= rect.y + rect.height);
+        }
+    }
+    assert_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn vertex_budget_met() {
+    let prefs = Prefs {
+        unit_size: 16,
+        padding: 0,
+        ppu: 1.0,
+        trim_transparent: false,
+        vertex_budget: 2400,
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    assert!(diced.sprites.iter().all(|s| s.vertices.len() <= 2400));
+    assert!(diced.sprites.iter().any(|s| !s.whole && s.grid.is_none()));
+    assert_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn duplicates_aliased() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        alias_duplicates: true,
+        ..Prefs::default()
+    };
+    let mut sources = SRC[ICONS].to_owned();
+    let copies = sources.iter().step_by(3).map(|s| SourceSprite {
+        id: format!("{}_copy", s.id),
+        ..s.to_owned()
+    });
+    sources.extend(copies.collect::<Vec<_>>());
+    let diced = sprite_dicing::dice(&sources, &prefs).unwrap();
+    assert_eq!(diced.aliases.len(), SRC[ICONS].len().div_ceil(3));
+    for alias in diced.aliases.iter() {
+        assert_eq!(alias.id, format!("{}_copy", alias.target));
+        assert!(diced.sprites.iter().any(|s| s.id == alias.target));
+    }
+    assert_repro(ICONS, diced, &prefs);
+    let packed = sprite_dicing::pack(&sources, &prefs).unwrap();
+    assert_eq!(packed.aliases.len(), SRC[ICONS].len().div_ceil(3));
+}
+
+#[test]
+fn welded_reproduced() {
+    let prefs = Prefs {
+        unit_size: 1,
+        padding: 0,
+        weld_vertices: true,
+        ..Prefs::default()
+    };
+    let mut diced = sprite_dicing::dice(&SRC[MONO], &prefs).unwrap();
+
+    // Restoring private quad vertices expected by the reproduction.
+    for sprite in diced.sprites.iter_mut() {
+        let indices: Vec<_> = sprite.indices.iter().collect();
+        let quads = indices.chunks(6).flat_map(|f| [f[0], f[1], f[2], f[4]]);
+        let (vertices, uvs) = quads
+            .map(|i| (sprite.vertices[i].to_owned(), sprite.uvs[i].to_owned()))
+            .unzip();
+        (sprite.vertices, sprite.uvs) = (vertices, uvs);
+    }
+    assert_repro(MONO, diced, &prefs);
+}
+
 #[test]
 fn atlas_not_square_when_not_forced() {
     let prefs = Prefs {
@@ -160,3 +437,131 @@ fn multiple_atlases_when_limited() {
     assert_eq!(diced.atlases.len(), 2);
     assert_repro(MONO, diced, &prefs);
 }
+
+#[test]
+fn atlases_uniform_when_forced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        atlas_size_limit: 1300,
+        atlas_uniform: true,
+        ..Prefs::default()
+    };
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    assert_eq!(diced.atlases.len(), 2);
+    assert!(
+        diced
+            .atlases
+            .iter()
+            .all(|a| a.width == 1156 && a.height == 1292)
+    );
+    assert_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn atlases_balanced_when_requested() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        atlas_size_limit: 1024,
+        atlas_balance: true,
+        ..Prefs::default()
+    };
+    let greedy = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        atlas_size_limit: 1024,
+        ..Prefs::default()
+    };
+    let greedy = sprite_dicing::dice(&SRC[ICONS], &greedy).unwrap();
+    let diced = sprite_dicing::dice(&SRC[ICONS], &prefs).unwrap();
+    assert!(greedy.atlases.len() > 1);
+    assert_eq!(diced.atlases.len(), greedy.atlases.len());
+    let units = diced.layouts.iter().map(|l| l.cells.len());
+    let (min, max) = (units.clone().min().unwrap(), units.max().unwrap());
+    assert!(max - min <= max / 10);
+    assert_repro(ICONS, diced, &prefs);
+}
+
+#[test]
+fn appended_reproduced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        atlas_size_limit: 1024,
+        ..Prefs::default()
+    };
+    let (first, last) = SRC[ICONS].split_at(SRC[ICONS].len() - 1);
+    let previous = sprite_dicing::dice(first, &prefs).unwrap();
+    let mut appended = sprite_dicing::append(&previous, last, &prefs).unwrap();
+    for (idx, layout) in previous.layouts.iter().enumerate() {
+        let cells = &appended.artifacts.layouts[idx].cells;
+        assert!(layout.cells.iter().all(|(hash, cell)| cells[hash] == *cell));
+        if !appended.changed.contains(&idx) {
+            assert_eq!(
+                previous.atlases[idx].pixels,
+                appended.artifacts.atlases[idx].pixels
+            );
+        }
+    }
+    assert_eq!(appended.artifacts.sprites.len(), 1);
+    appended.artifacts.sprites.extend(previous.sprites);
+    assert_repro(ICONS, appended.artifacts, &prefs);
+}
+
+#[test]
+fn patched_reproduced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        atlas_size_limit: 1024,
+        ..Prefs::default()
+    };
+    let (first, _) = SRC[ICONS].split_at(SRC[ICONS].len() - 1);
+    let shipped = sprite_dicing::dice(first, &prefs).unwrap();
+    let patched = sprite_dicing::patch(&shipped.layouts, &SRC[ICONS], &prefs).unwrap();
+    assert!(!patched.atlases.is_empty());
+    for hash in patched.layouts.iter().flat_map(|l| l.cells.keys()) {
+        assert!(shipped.layouts.iter().all(|l| !l.cells.contains_key(hash)));
+    }
+    let mut atlases = shipped.atlases;
+    atlases.extend(patched.atlases);
+    let arts = Artifacts {
+        atlases,
+        layouts: vec![],
+        sprites: patched.sprites,
+        aliases: vec![],
+    };
+    assert_repro(ICONS, arts, &prefs);
+}
+
+#[test]
+fn diced_with_library_reproduced() {
+    let prefs = Prefs {
+        ppu: 1.0,
+        trim_transparent: false,
+        ..Prefs::default()
+    };
+    let (kit, product) = SRC[ICONS].split_at(SRC[ICONS].len() / 2);
+    let base = sprite_dicing::dice(kit, &prefs).unwrap();
+    let bytes = Library::new(&base, &prefs).unwrap().to_bytes();
+    let library = Library::from_bytes(&bytes).unwrap();
+    let diced = sprite_dicing::dice_with_library(&library, &SRC[ICONS], &prefs).unwrap();
+    for hash in diced.layouts.iter().flat_map(|l| l.cells.keys()) {
+        assert!(library.layouts.iter().all(|l| !l.cells.contains_key(hash)));
+    }
+    assert!(
+        product
+            .iter()
+            .all(|s| diced.sprites.iter().any(|d| d.id == s.id))
+    );
+    let mut atlases = base.atlases;
+    atlases.extend(diced.atlases);
+    let arts = Artifacts {
+        atlases,
+        layouts: vec![],
+        sprites: diced.sprites,
+        aliases: vec![],
+    };
+    assert_repro(ICONS, arts, &prefs);
+}
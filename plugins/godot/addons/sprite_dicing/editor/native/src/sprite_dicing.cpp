@@ -40,14 +40,33 @@ struct CRect { float x, y, width, height; };
 struct CVertex { float x, y; };
 struct CUv { float u, v; };
 struct CTexture { uint32_t width; uint32_t height; CSlice pixels; };
-struct CSourceSprite { const char* id; CTexture texture; bool has_pivot; CPivot pivot; };
-struct CPrefs { uint32_t unit_size; uint32_t padding; float uv_inset; bool trim_transparent; uint32_t atlas_size_limit; bool atlas_square; bool atlas_pot; float ppu; CPivot pivot; bool has_progress_callback; void* progress_callback; };
-struct CDicedSprite { const char* id; uint64_t atlas; CSlice vertices; CSlice uvs; CSlice indices; CRect rect; CPivot pivot; };
-struct CArtifacts { CSlice atlases; CSlice sprites; };
+struct COrigin { int32_t anchor; float x, y; };
+struct CBorder { uint32_t left, top, right, bottom; };
+struct CGrid { uint32_t columns; uint32_t rows; CSlice cells; };
+struct CQuantized { CVertex origin; CSlice vertices; CSlice uvs; };
+struct CSourceSprite { const char* id; CTexture texture; bool has_pivot; CPivot pivot; bool has_origin; COrigin origin; bool has_group; const char* group; bool has_ppu; float ppu; bool has_border; CBorder border; };
+struct CPrefs { uint32_t unit_size; uint32_t padding; float uv_inset; bool trim_transparent; float reuse_threshold; uint32_t atlas_size_limit; bool atlas_square; bool atlas_pot; bool atlas_uniform; bool atlas_balance; float ppu; CPivot pivot; bool has_origin; COrigin origin; bool y_up; bool uv_bottom_left; bool clockwise; bool build_quads; bool weld_vertices; bool quantize; bool build_instances; bool merge_instance_uvs; bool build_indirection; bool build_outline; uint8_t outline_threshold; float outline_tolerance; uint32_t lod_count; uint32_t vertex_budget; bool alias_duplicates; bool has_progress_callback; void* progress_callback; };
+struct CDicedSprite { const char* id; uint64_t atlas; CSlice atlas_indices; uint64_t layer; CSlice vertices; CSlice uvs; CSlice indices_u16; CSlice indices_u32; CRect rect; CPivot pivot; bool has_group; const char* group; bool whole; bool has_indirection; CTexture indirection; bool has_grid; CGrid grid; CSlice instances; CSlice instance_uvs; bool has_quantized; CQuantized quantized; CSlice outline; bool has_border; CBorder border; CSlice slices; CSlice lods; };
+struct CArtifacts { CSlice atlases; CSlice occupancy; CSlice sprites; CSlice aliases; };
 struct CResult { const char* error; CArtifacts ok; };
 
 typedef CResult (*DiceFunc)(CSlice sprites, CPrefs prefs);
 
+// Indices are stored as 16-bit when all of them fit, in which case the 32-bit slice is empty.
+static PackedInt32Array to_indices(const CSlice& u16, const CSlice& u32) {
+    PackedInt32Array indices;
+    if (u16.len > 0) {
+        const uint16_t* data = static_cast<const uint16_t*>(u16.ptr);
+        indices.resize(u16.len);
+        for (uint64_t i = 0; i < u16.len; i++) indices.set(i, (int32_t)data[i]);
+    } else {
+        const uint32_t* data = static_cast<const uint32_t*>(u32.ptr);
+        indices.resize(u32.len);
+        for (uint64_t i = 0; i < u32.len; i++) indices.set(i, (int32_t)data[i]);
+    }
+    return indices;
+}
+
 void SpriteDicing::_bind_methods() {
     ClassDB::bind_method(D_METHOD("is_available"), &SpriteDicing::is_available);
     ClassDB::bind_method(D_METHOD("dice", "sources", "prefs"), &SpriteDicing::dice);
@@ -131,6 +150,14 @@ Dictionary SpriteDicing::dice(const Array& sources, const Dictionary& prefs) {
         Vector2 pivot = src["pivot"];
         c_sprite.pivot.x = pivot.x;
         c_sprite.pivot.y = pivot.y;
+        c_sprite.has_origin = false;
+        c_sprite.origin = { -1, 0, 0 };
+        c_sprite.has_group = false;
+        c_sprite.group = nullptr;
+        c_sprite.has_ppu = false;
+        c_sprite.ppu = 0;
+        c_sprite.has_border = false;
+        c_sprite.border = { 0, 0, 0, 0 };
 
         c_sprites.push_back(c_sprite);
     }
@@ -140,13 +167,33 @@ Dictionary SpriteDicing::dice(const Array& sources, const Dictionary& prefs) {
     c_prefs.padding = (uint32_t)(int)prefs["padding"];
     c_prefs.uv_inset = prefs["uv_inset"];
     c_prefs.trim_transparent = prefs["trim_transparent"];
+    c_prefs.reuse_threshold = 0;
     c_prefs.atlas_size_limit = (uint32_t)(int)prefs["atlas_size_limit"];
     c_prefs.atlas_square = prefs["atlas_square"];
     c_prefs.atlas_pot = prefs["atlas_pot"];
+    c_prefs.atlas_uniform = false;
+    c_prefs.atlas_balance = false;
     c_prefs.ppu = prefs["ppu"];
     Vector2 pivot = prefs["pivot"];
     c_prefs.pivot.x = pivot.x;
     c_prefs.pivot.y = pivot.y;
+    c_prefs.has_origin = false;
+    c_prefs.origin = { -1, 0, 0 };
+    c_prefs.y_up = false;
+    c_prefs.uv_bottom_left = false;
+    c_prefs.clockwise = false;
+    c_prefs.build_quads = true;
+    c_prefs.weld_vertices = false;
+    c_prefs.quantize = false;
+    c_prefs.build_instances = false;
+    c_prefs.merge_instance_uvs = false;
+    c_prefs.build_indirection = false;
+    c_prefs.build_outline = false;
+    c_prefs.outline_threshold = 128;
+    c_prefs.outline_tolerance = 1;
+    c_prefs.lod_count = 0;
+    c_prefs.vertex_budget = 0;
+    c_prefs.alias_duplicates = false;
     c_prefs.has_progress_callback = false;
     c_prefs.progress_callback = nullptr;
 
@@ -203,13 +250,7 @@ Dictionary SpriteDicing::dice(const Array& sources, const Dictionary& prefs) {
         }
         sprite["uvs"] = uvs;
 
-        PackedInt32Array indices;
-        const uint64_t* idx_data = static_cast<const uint64_t*>(diced_sprites[i].indices.ptr);
-        indices.resize(diced_sprites[i].indices.len);
-        for (uint64_t j = 0; j < diced_sprites[i].indices.len; j++) {
-            indices.set(j, (int32_t)idx_data[j]);
-        }
-        sprite["indices"] = indices;
+        sprite["indices"] = to_indices(diced_sprites[i].indices_u16, diced_sprites[i].indices_u32);
 
         sprite["rect"] = Rect2(
             diced_sprites[i].rect.x,
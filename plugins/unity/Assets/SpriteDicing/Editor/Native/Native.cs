@@ -136,6 +136,35 @@ namespace SpriteDicing
             [MarshalAs(UnmanagedType.I1)]
             public bool has_pivot;
             public CPivot pivot;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_origin;
+            public COrigin origin;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_group;
+            public IntPtr group;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_ppu;
+            public float ppu;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_border;
+            public CBorder border;
+        }
+
+        [StructLayout(LayoutKind.Sequential)]
+        private struct COrigin
+        {
+            public int anchor;
+            public float x;
+            public float y;
+        }
+
+        [StructLayout(LayoutKind.Sequential)]
+        private struct CBorder
+        {
+            public uint left;
+            public uint top;
+            public uint right;
+            public uint bottom;
         }
 
         [StructLayout(LayoutKind.Sequential)]
@@ -154,14 +183,48 @@ namespace SpriteDicing
             public float uv_inset;
             [MarshalAs(UnmanagedType.I1)]
             public bool trim_transparent;
+            public float reuse_threshold;
             public uint atlas_size_limit;
             [MarshalAs(UnmanagedType.I1)]
             public bool atlas_square;
             [MarshalAs(UnmanagedType.I1)]
             public bool atlas_pot;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool atlas_uniform;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool atlas_balance;
             public float ppu;
             public CPivot pivot;
             [MarshalAs(UnmanagedType.I1)]
+            public bool has_origin;
+            public COrigin origin;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool y_up;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool uv_bottom_left;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool clockwise;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool build_quads;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool weld_vertices;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool quantize;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool build_instances;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool merge_instance_uvs;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool build_indirection;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool build_outline;
+            public byte outline_threshold;
+            public float outline_tolerance;
+            public uint lod_count;
+            public uint vertex_budget;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool alias_duplicates;
+            [MarshalAs(UnmanagedType.I1)]
             public bool has_progress_callback;
             public CProgressCallback progress_callback;
         }
@@ -170,7 +233,9 @@ namespace SpriteDicing
         private struct CArtifacts
         {
             public CSlice atlases;
+            public CSlice occupancy;
             public CSlice sprites;
+            public CSlice aliases;
         }
 
         [StructLayout(LayoutKind.Sequential)]
@@ -185,11 +250,52 @@ namespace SpriteDicing
         {
             public IntPtr id;
             public ulong atlas;
+            public CSlice atlas_indices;
+            public ulong layer;
             public CSlice vertices;
             public CSlice uvs;
-            public CSlice indices;
+            public CSlice indices_u16;
+            public CSlice indices_u32;
             public CRect rect;
             public CPivot pivot;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_group;
+            public IntPtr group;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool whole;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_indirection;
+            public CTexture indirection;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_grid;
+            public CGrid grid;
+            public CSlice instances;
+            public CSlice instance_uvs;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_quantized;
+            public CQuantized quantized;
+            public CSlice outline;
+            [MarshalAs(UnmanagedType.I1)]
+            public bool has_border;
+            public CBorder border;
+            public CSlice slices;
+            public CSlice lods;
+        }
+
+        [StructLayout(LayoutKind.Sequential)]
+        private struct CGrid
+        {
+            public uint columns;
+            public uint rows;
+            public CSlice cells;
+        }
+
+        [StructLayout(LayoutKind.Sequential)]
+        private struct CQuantized
+        {
+            public CVertex origin;
+            public CSlice vertices;
+            public CSlice uvs;
         }
 
         [StructLayout(LayoutKind.Sequential)]
@@ -268,7 +374,11 @@ namespace SpriteDicing
             atlas_square = prefs.AtlasSquare,
             atlas_pot = prefs.AtlasPOT,
             pivot = MarshalPivot(prefs.Pivot),
+            origin = new COrigin { anchor = -1 },
             ppu = prefs.PPU,
+            build_quads = true,
+            outline_threshold = 128,
+            outline_tolerance = 1,
             has_progress_callback = prefs.OnProgress != null,
             progress_callback = p => prefs.OnProgress(MarshalProgress(p))
         };
@@ -318,7 +428,8 @@ namespace SpriteDicing
             pivot = new CPivot {
                 x = s.Pivot.GetValueOrDefault().X,
                 y = s.Pivot.GetValueOrDefault().Y
-            }
+            },
+            origin = new COrigin { anchor = -1 }
         };
 
         private static DicedSprite MarshalDicedSprite (CDicedSprite c, List<IntPtr> pts) => new() {
@@ -326,7 +437,7 @@ namespace SpriteDicing
             Atlas = (int)c.atlas,
             Vertices = MarshalSlice<CVertex>(c.vertices, pts).Select(MarshalVertex).ToArray(),
             UVs = MarshalSlice<CUv>(c.uvs, pts).Select(MarshalUV).ToArray(),
-            Indices = MarshalIndices(c.indices),
+            Indices = MarshalIndices(c.indices_u16, c.indices_u32),
             Rect = MarshalRect(c.rect),
             Pivot = MarshalPivot(c.pivot)
         };
@@ -347,12 +458,21 @@ namespace SpriteDicing
             V = c.v
         };
 
-        private static int[] MarshalIndices (CSlice c)
+        private static int[] MarshalIndices (CSlice u16, CSlice u32)
         {
-            var array = new int[c.len];
-            var longPtr = (ulong*)c.ptr;
+            // Indices are stored as 16-bit when all of them fit, in which case the 32-bit slice is empty.
+            if (u16.len > 0)
+            {
+                var array16 = new int[u16.len];
+                var shortPtr = (ushort*)u16.ptr;
+                for (int i = 0; i < array16.Length; ++i)
+                    array16[i] = *shortPtr++;
+                return array16;
+            }
+            var array = new int[u32.len];
+            var intPtr = (uint*)u32.ptr;
             for (int i = 0; i < array.Length; ++i)
-                array[i] = (int)*longPtr++;
+                array[i] = (int)*intPtr++;
             return array;
         }
 